@@ -1,12 +1,15 @@
+#[cfg(feature = "c_backend")]
 const HDRHISTO_SRC: &str = "HdrHistogram_c/src";
 
 // From HdrHistogram_c/src/CMakeLists.txt
+#[cfg(feature = "c_backend")]
 const HDR_SRCS: &[&str] = &[
     "hdr_encoding.c",
     "hdr_histogram.c",
-    #[cfg(feature = "hdr_log")]
+    // Encoding is done in Rust with `rust_deflate`, so the zlib based implementation isn't needed.
+    #[cfg(all(feature = "hdr_log", not(feature = "rust_deflate")))]
     "hdr_histogram_log.c",
-    #[cfg(not(feature = "hdr_log"))]
+    #[cfg(any(not(feature = "hdr_log"), feature = "rust_deflate"))]
     "hdr_histogram_log_no_op.c",
     "hdr_interval_recorder.c",
     "hdr_thread.c",
@@ -14,6 +17,7 @@ const HDR_SRCS: &[&str] = &[
     "hdr_writer_reader_phaser.c",
 ];
 
+#[cfg(feature = "c_backend")]
 const HDR_INCLUDES: &[&str] = &[
     "hdr_histogram.h",
     "hdr_histogram_log.h",
@@ -23,36 +27,139 @@ const HDR_INCLUDES: &[&str] = &[
     "hdr_writer_reader_phaser.h",
 ];
 
+#[cfg(feature = "c_backend")]
 const RUST_GLUE: &str = "src/glue.c";
+#[cfg(feature = "c_backend")]
 const RUST_GLUE_H: &str = "src/glue.h";
 
+// Releases of the system library whose functions and struct layouts match the bridged signatures
+// in src/lib.rs and the field accesses in src/glue.c.
+#[cfg(feature = "system_lib")]
+const SYSTEM_VERSIONS: std::ops::Range<&str> = "0.11.3".."0.12";
+
+/// A usable system HdrHistogram_c.
+#[cfg(feature = "c_backend")]
+struct SystemLib {
+    include_paths: Vec<std::path::PathBuf>,
+    link_paths: Vec<std::path::PathBuf>,
+    libs: Vec<String>,
+}
+
+// Environment pkg-config consults, each of which may also be set for the target or host only.
+#[cfg(feature = "system_lib")]
+const PKG_CONFIG_ENV: &[&str] = &[
+    "PKG_CONFIG",
+    "PKG_CONFIG_PATH",
+    "PKG_CONFIG_LIBDIR",
+    "PKG_CONFIG_SYSROOT_DIR",
+    "PKG_CONFIG_ALLOW_CROSS",
+    "PKG_CONFIG_ALL_STATIC",
+    "PKG_CONFIG_ALL_DYNAMIC",
+    "HDR_HISTOGRAM_NO_PKG_CONFIG",
+    "HDR_HISTOGRAM_STATIC",
+    "HDR_HISTOGRAM_DYNAMIC",
+];
+
+#[cfg(feature = "system_lib")]
+fn probe_system_lib() -> Option<SystemLib> {
+    // With cargo metadata turned off below, rebuilds on environment changes are our job.
+    let target = std::env::var("TARGET").unwrap_or_default();
+    for var in PKG_CONFIG_ENV {
+        println!("cargo:rerun-if-env-changed={}", var);
+        println!("cargo:rerun-if-env-changed={}_{}", var, target);
+        println!("cargo:rerun-if-env-changed={}_{}", var, target.replace('-', "_"));
+        println!("cargo:rerun-if-env-changed=HOST_{}", var);
+        println!("cargo:rerun-if-env-changed=TARGET_{}", var);
+    }
+
+    // Link metadata is emitted by hand after the glue library, so that it comes later on the
+    // linker command line.
+    let res = pkg_config::Config::new()
+        .range_version(SYSTEM_VERSIONS)
+        .cargo_metadata(false)
+        .probe("hdr_histogram");
+
+    match res {
+        Ok(lib) => Some(SystemLib {
+            include_paths: lib.include_paths,
+            link_paths: lib.link_paths,
+            libs: lib.libs,
+        }),
+        Err(err) => {
+            println!(
+                "cargo:warning=no compatible system hdr_histogram, using bundled sources: {}",
+                err
+            );
+            None
+        }
+    }
+}
+
+#[cfg(all(feature = "c_backend", not(feature = "system_lib")))]
+fn probe_system_lib() -> Option<SystemLib> {
+    None
+}
+
+// The pure Rust backend has nothing to build.
+#[cfg(not(feature = "c_backend"))]
+fn main() {}
+
+#[cfg(feature = "c_backend")]
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut srcs: Vec<_> = HDR_SRCS
-        .iter()
-        .map(|src| format!("{}/{}", HDRHISTO_SRC, src))
-        .collect();
-    srcs.push(RUST_GLUE.to_string());
-
-    let mut headers: Vec<_> = HDR_INCLUDES
-        .iter()
-        .map(|hdr| format!("{}/{}", HDRHISTO_SRC, hdr))
-        .collect();
-    headers.push(RUST_GLUE_H.to_string());
+    let system = probe_system_lib();
+
+    let mut srcs = vec![RUST_GLUE.to_string()];
+    let mut headers = vec![RUST_GLUE_H.to_string()];
+    let mut build = cxx_build::bridge("src/lib.rs");
+
+    match &system {
+        Some(system) => {
+            // The bridge and glue find the installed headers through the include path, which
+            // may or may not be reported with their hdr/ subdirectory.
+            for path in &system.include_paths {
+                build.include(path).include(path.join("hdr"));
+            }
+            build.define("HDR_RUST_SYSTEM_LIB", None);
+        }
+        None => {
+            srcs.extend(HDR_SRCS.iter().map(|src| format!("{}/{}", HDRHISTO_SRC, src)));
+            headers.extend(HDR_INCLUDES.iter().map(|hdr| format!("{}/{}", HDRHISTO_SRC, hdr)));
+            build.include(HDRHISTO_SRC);
+        }
+    }
 
     eprintln!("srcs {:?}", srcs);
     eprintln!("headers {:?}", headers);
 
-    cxx_build::bridge("src/lib.rs")
+    // Don't shadow the system libhdr_histogram with the glue.
+    let lib_name = if system.is_some() {
+        "hdr_histogram_glue"
+    } else {
+        "hdr_histogram"
+    };
+
+    build
         .files(&srcs)
-        .include(HDRHISTO_SRC)
         .include(std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set"))
         .cpp(false)
-        .compile("hdr_histogram");
+        .compile(lib_name);
 
-    println!("cargo:rustc-link-lib=static=hdr_histogram");
-    #[cfg(feature = "hdr_log")]
-    println!("cargo:rustc-link-lib=z");
-    println!("cargo:rustc-link-lib=m");
+    println!("cargo:rustc-link-lib=static={}", lib_name);
+    match &system {
+        Some(system) => {
+            for path in &system.link_paths {
+                println!("cargo:rustc-link-search=native={}", path.display());
+            }
+            for lib in &system.libs {
+                println!("cargo:rustc-link-lib={}", lib);
+            }
+        }
+        None => {
+            #[cfg(all(feature = "hdr_log", not(feature = "rust_deflate")))]
+            println!("cargo:rustc-link-lib=z");
+            println!("cargo:rustc-link-lib=m");
+        }
+    }
 
     for s in &srcs {
         println!("cargo:rerun-if-changed={}", s);
@@ -0,0 +1,99 @@
+//! A `Histogram` which can be recorded into concurrently.
+
+use crate::{ffi, Histogram, HistogramErr};
+
+/// A histogram which can be shared between threads and recorded into without locking.
+///
+/// Recording uses the `hdr_record_*_atomic` family of functions, which update counts with atomic
+/// operations, so the recording methods only need `&self`. Reporting is done by taking a
+/// [`snapshot`](#method.snapshot) into a plain [`Histogram`](struct.Histogram.html).
+///
+/// ```
+/// # use hdrhistogram_c::AtomicHistogram;
+/// let h = AtomicHistogram::new(1, 1000000, 2).unwrap();
+///
+/// std::thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| for v in 1..=100 { h.record_value(v); });
+///     }
+/// });
+///
+/// assert_eq!(h.snapshot().total_count(), 400);
+/// ```
+pub struct AtomicHistogram(Histogram);
+
+unsafe impl Sync for AtomicHistogram {}
+
+impl AtomicHistogram {
+    /// Create a new `AtomicHistogram`, with the same parameters as `Histogram::new`.
+    pub fn new(
+        lowest_discernible_value: i64,
+        highest_trackable_value: i64,
+        significant_figures: i32,
+    ) -> Result<Self, HistogramErr> {
+        Histogram::new(
+            lowest_discernible_value,
+            highest_trackable_value,
+            significant_figures,
+        )
+        .map(AtomicHistogram)
+    }
+
+    /// Atomically record a value. Returns false if the value is out of range.
+    #[inline]
+    pub fn record_value(&self, value: i64) -> bool {
+        unsafe { ffi::hdr_record_value_atomic((self.0).0, value) }
+    }
+
+    /// Atomically record multiple counts of a value. Returns false if the value is out of range.
+    #[inline]
+    pub fn record_values(&self, value: i64, count: i64) -> bool {
+        unsafe { ffi::hdr_record_values_atomic((self.0).0, value, count) }
+    }
+
+    /// Atomically record a value, correcting for coordinated omission.
+    #[inline]
+    pub fn record_corrected_value(&self, value: i64, expected_interval: i64) -> bool {
+        unsafe { ffi::hdr_record_corrected_value_atomic((self.0).0, value, expected_interval) }
+    }
+
+    /// Atomically record multiple counts of a value, correcting for coordinated omission.
+    #[inline]
+    pub fn record_corrected_values(&self, value: i64, count: i64, expected_interval: i64) -> bool {
+        unsafe {
+            ffi::hdr_record_corrected_values_atomic((self.0).0, value, count, expected_interval)
+        }
+    }
+
+    /// Copy the current state into a new `Histogram`.
+    ///
+    /// The copy isn't atomic. Buckets are read one at a time, without synchronizing with
+    /// concurrent recorders, so the snapshot doesn't correspond to any single point in time: a
+    /// value recorded during the copy may be missing while a later one is included. Its total
+    /// count, minimum and maximum are recomputed from the copied counts, so they at least agree
+    /// with its buckets. For exact, non-overlapping intervals use an
+    /// [`IntervalRecorder`](struct.IntervalRecorder.html), which swaps histograms under a phaser
+    /// instead.
+    pub fn snapshot(&self) -> Histogram {
+        let h = self.0.clone();
+        unsafe { ffi::hdr_rust_reset_internal_counters(h.0) };
+        h
+    }
+
+    /// Zero all histogram state. This requires exclusive access, so there can be no concurrent
+    /// recorders.
+    pub fn reset(&mut self) {
+        self.0.reset()
+    }
+
+    /// Return the underlying `Histogram`.
+    pub fn into_histogram(self) -> Histogram {
+        self.0
+    }
+}
+
+impl From<Histogram> for AtomicHistogram {
+    fn from(h: Histogram) -> Self {
+        AtomicHistogram(h)
+    }
+}
@@ -0,0 +1,113 @@
+//! Equality, `Debug` and bucket-by-bucket comparison of histograms.
+
+use std::fmt;
+
+use crate::{ffi, Histogram, HistogramErr};
+
+/// A bucket whose counts differ between two histograms, as returned by
+/// [`Histogram::diff`](struct.Histogram.html#method.diff).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BucketDiff {
+    /// Lowest value counted in the bucket.
+    pub value: i64,
+    /// Count in the histogram `diff` was called on.
+    pub count: i64,
+    /// Count in the other histogram.
+    pub other_count: i64,
+}
+
+impl Histogram {
+    /// List the buckets whose counts differ from those of `other`, in increasing order of value.
+    ///
+    /// The histograms must have the same unit magnitude and sub-bucket count so their buckets line
+    /// up, but their highest trackable values may differ; buckets only one of them has are
+    /// compared with a count of 0.
+    ///
+    /// ```
+    /// # use hdrhistogram_c::{BucketDiff, Histogram};
+    /// let mut a = Histogram::new(1, 100000, 3).unwrap();
+    /// a.record_values(10, 2);
+    /// let mut b = a.clone();
+    /// b.record_value(20);
+    ///
+    /// assert_eq!(
+    ///     a.diff(&b).unwrap(),
+    ///     vec![BucketDiff { value: 20, count: 0, other_count: 1 }]
+    /// );
+    /// ```
+    pub fn diff(&self, other: &Histogram) -> Result<Vec<BucketDiff>, HistogramErr> {
+        if self.unit_magnitude() != other.unit_magnitude()
+            || self.sub_bucket_count() != other.sub_bucket_count()
+        {
+            return Err(HistogramErr::IncompatibleHistograms);
+        }
+
+        let counts_len = self.get_counts_len() as i32;
+        let other_counts_len = other.get_counts_len() as i32;
+        let count_at = |h: &Histogram, len: i32, i: i32| {
+            if i < len {
+                unsafe { ffi::hdr_count_at_index(h.0, i) }
+            } else {
+                0
+            }
+        };
+
+        let mut diffs = Vec::new();
+        for i in 0..counts_len.max(other_counts_len) {
+            let count = count_at(self, counts_len, i);
+            let other_count = count_at(other, other_counts_len, i);
+
+            if count != other_count {
+                let h = if i < counts_len { self } else { other };
+                diffs.push(BucketDiff {
+                    value: unsafe { ffi::hdr_value_at_index(h.0, i) },
+                    count,
+                    other_count,
+                });
+            }
+        }
+
+        Ok(diffs)
+    }
+}
+
+/// Histograms are equal if they have the same lowest discernible value, highest trackable value,
+/// significant figures, conversion ratio and counts. The conversion ratio is the unit of a
+/// [`DurationHistogram`](struct.DurationHistogram.html), so the same counts in different units
+/// aren't equal. Whether they auto-resize isn't compared.
+impl PartialEq for Histogram {
+    fn eq(&self, other: &Histogram) -> bool {
+        self.lowest_discernible_value() == other.lowest_discernible_value()
+            && self.highest_trackable_value() == other.highest_trackable_value()
+            && self.significant_figures() == other.significant_figures()
+            && unsafe {
+                ffi::hdr_rust_conversion_ratio(self.0) == ffi::hdr_rust_conversion_ratio(other.0)
+            }
+            && self.total_count() == other.total_count()
+            && (0..self.get_counts_len() as i32).all(|i| unsafe {
+                ffi::hdr_count_at_index(self.0, i) == ffi::hdr_count_at_index(other.0, i)
+            })
+    }
+}
+
+impl Eq for Histogram {}
+
+/// Shows the configuration and summary statistics, rather than the counts.
+impl fmt::Debug for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("Histogram");
+        s.field("lowest_discernible_value", &self.lowest_discernible_value())
+            .field("highest_trackable_value", &self.highest_trackable_value())
+            .field("significant_figures", &self.significant_figures())
+            .field("auto_resize", &self.is_auto_resize())
+            .field("total_count", &self.total_count());
+
+        if self.total_count() > 0 {
+            s.field("min", &self.min())
+                .field("max", &self.max())
+                .field("mean", &self.mean());
+        }
+
+        s.finish()
+    }
+}
@@ -0,0 +1,183 @@
+//! Histogram configuration, and the bucket layout it implies.
+
+use crate::{ffi, Histogram, HistogramErr};
+
+/// Builder for [`Histogram`](struct.Histogram.html) parameters.
+///
+/// The parameters can be validated, and the bucket layout and memory footprint they imply
+/// inspected, before anything is allocated.
+///
+/// ```
+/// # use hdrhistogram_c::HistogramConfig;
+/// let config = HistogramConfig::new()
+///     .highest_trackable_value(3600 * 1000 * 1000)
+///     .significant_figures(3);
+///
+/// let buckets = config.bucket_config().unwrap();
+/// assert_eq!(buckets.sub_bucket_count, 2048);
+///
+/// let h = config.build().unwrap();
+/// assert_eq!(h.get_memory_size(), buckets.memory_size);
+/// assert_eq!(h.config(), config);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistogramConfig {
+    lowest_discernible_value: i64,
+    highest_trackable_value: i64,
+    significant_figures: i32,
+    auto_resize: bool,
+}
+
+/// Bucket layout of a histogram, as computed by `hdr_calculate_bucket_config`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BucketConfig {
+    /// Base 2 logarithm of the largest power of two not above the lowest discernible value.
+    pub unit_magnitude: i32,
+    /// Number of sub-buckets in each bucket.
+    pub sub_bucket_count: i32,
+    /// Number of buckets.
+    pub bucket_count: i32,
+    /// Length of the counts array.
+    pub counts_len: i32,
+    /// Bytes of memory the histogram will use.
+    pub memory_size: usize,
+}
+
+impl HistogramConfig {
+    /// Create a configuration with a lowest discernible value of 1, a highest trackable value of
+    /// 2, 3 significant figures and auto-resizing enabled. Like HdrHistogram Java's default
+    /// `Histogram`, it starts out small and grows to fit the values recorded. For a fixed range,
+    /// set the highest trackable value and disable auto-resizing.
+    pub fn new() -> Self {
+        HistogramConfig {
+            lowest_discernible_value: 1,
+            highest_trackable_value: 2,
+            significant_figures: 3,
+            auto_resize: true,
+        }
+    }
+
+    /// Set the lowest value which can be discerned from 0. Must be at least 1.
+    pub fn lowest_discernible_value(mut self, value: i64) -> Self {
+        self.lowest_discernible_value = value;
+        self
+    }
+
+    /// Set the highest value which can be recorded. Must be at least twice the lowest discernible
+    /// value.
+    pub fn highest_trackable_value(mut self, value: i64) -> Self {
+        self.highest_trackable_value = value;
+        self
+    }
+
+    /// Set the number of significant decimal digits of precision, in the range 1..=5.
+    pub fn significant_figures(mut self, significant_figures: i32) -> Self {
+        self.significant_figures = significant_figures;
+        self
+    }
+
+    /// Set whether the histogram grows to fit out of range values. See
+    /// [`Histogram::set_auto_resize`](struct.Histogram.html#method.set_auto_resize).
+    pub fn auto_resize(mut self, enabled: bool) -> Self {
+        self.auto_resize = enabled;
+        self
+    }
+
+    /// The lowest value which can be discerned from 0.
+    pub fn get_lowest_discernible_value(&self) -> i64 {
+        self.lowest_discernible_value
+    }
+
+    /// The highest value which can be recorded, before any auto-resizing.
+    pub fn get_highest_trackable_value(&self) -> i64 {
+        self.highest_trackable_value
+    }
+
+    /// The number of significant decimal digits of precision.
+    pub fn get_significant_figures(&self) -> i32 {
+        self.significant_figures
+    }
+
+    /// Whether the histogram grows to fit out of range values.
+    pub fn is_auto_resize(&self) -> bool {
+        self.auto_resize
+    }
+
+    /// A configuration which can hold the values of all `histograms` at their precision: the
+    /// lowest of their lowest discernible values, the highest of their highest trackable values,
+    /// and the most significant figures. Without any histograms, this is the default.
+    pub fn covering<'a>(histograms: impl IntoIterator<Item = &'a Histogram>) -> Self {
+        histograms
+            .into_iter()
+            .map(|h| h.config().auto_resize(false))
+            .reduce(|a, b| HistogramConfig {
+                lowest_discernible_value: a
+                    .lowest_discernible_value
+                    .min(b.lowest_discernible_value),
+                highest_trackable_value: a.highest_trackable_value.max(b.highest_trackable_value),
+                significant_figures: a.significant_figures.max(b.significant_figures),
+                auto_resize: false,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Validate the parameters and compute the resulting bucket layout.
+    pub fn bucket_config(&self) -> Result<BucketConfig, HistogramErr> {
+        HistogramErr::check_params(
+            self.lowest_discernible_value,
+            self.highest_trackable_value,
+            self.significant_figures,
+        )?;
+
+        let mut cfg = BucketConfig {
+            unit_magnitude: 0,
+            sub_bucket_count: 0,
+            bucket_count: 0,
+            counts_len: 0,
+            memory_size: 0,
+        };
+        let res = unsafe {
+            ffi::hdr_rust_calculate_bucket_config(
+                self.lowest_discernible_value,
+                self.highest_trackable_value,
+                self.significant_figures,
+                &mut cfg.unit_magnitude,
+                &mut cfg.sub_bucket_count,
+                &mut cfg.bucket_count,
+                &mut cfg.counts_len,
+            )
+        };
+
+        if res != 0 {
+            return Err(HistogramErr::init(res));
+        }
+
+        cfg.memory_size = unsafe { ffi::hdr_rust_memory_size(cfg.counts_len) };
+
+        Ok(cfg)
+    }
+
+    /// Bytes of memory a histogram with this configuration will use.
+    pub fn memory_size(&self) -> Result<usize, HistogramErr> {
+        self.bucket_config().map(|cfg| cfg.memory_size)
+    }
+
+    /// Allocate a histogram with this configuration.
+    pub fn build(&self) -> Result<Histogram, HistogramErr> {
+        let mut h = Histogram::new(
+            self.lowest_discernible_value,
+            self.highest_trackable_value,
+            self.significant_figures,
+        )?;
+        h.set_auto_resize(self.auto_resize);
+
+        Ok(h)
+    }
+}
+
+impl Default for HistogramConfig {
+    fn default() -> Self {
+        HistogramConfig::new()
+    }
+}
@@ -0,0 +1,257 @@
+//! Histograms of `Duration`s in a declared time unit.
+
+use std::{convert::TryFrom, fmt, time::Duration};
+
+use crate::{ffi, Histogram, HistogramErr};
+
+/// Unit of the values recorded in a [`DurationHistogram`](struct.DurationHistogram.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TimeUnit {
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+}
+
+impl TimeUnit {
+    /// Convert `duration` to a whole number of this unit, rounding down, and saturating at
+    /// `i64::MAX`.
+    pub fn from_duration(self, duration: Duration) -> i64 {
+        let units = match self {
+            TimeUnit::Nanoseconds => duration.as_nanos(),
+            TimeUnit::Microseconds => duration.as_micros(),
+            TimeUnit::Milliseconds => duration.as_millis(),
+        };
+        i64::try_from(units).unwrap_or(i64::MAX)
+    }
+
+    /// Convert a number of this unit to a `Duration`. Negative values become zero.
+    pub fn to_duration(self, units: i64) -> Duration {
+        let units = units.max(0) as u64;
+        match self {
+            TimeUnit::Nanoseconds => Duration::from_nanos(units),
+            TimeUnit::Microseconds => Duration::from_micros(units),
+            TimeUnit::Milliseconds => Duration::from_millis(units),
+        }
+    }
+
+    /// Convert a fractional number of this unit, such as a mean, to a `Duration`, saturating at
+    /// zero and `u64::MAX` nanoseconds.
+    pub fn to_duration_f64(self, units: f64) -> Duration {
+        Duration::from_nanos((units * self.nanos() as f64) as u64)
+    }
+
+    fn nanos(self) -> u64 {
+        match self {
+            TimeUnit::Nanoseconds => 1,
+            TimeUnit::Microseconds => 1_000,
+            TimeUnit::Milliseconds => 1_000_000,
+        }
+    }
+
+    /// Seconds per unit, stored as the encoded histogram's conversion ratio.
+    fn conversion_ratio(self) -> f64 {
+        match self {
+            TimeUnit::Nanoseconds => 1e-9,
+            TimeUnit::Microseconds => 1e-6,
+            TimeUnit::Milliseconds => 1e-3,
+        }
+    }
+
+    fn from_conversion_ratio(ratio: f64) -> Option<TimeUnit> {
+        [
+            TimeUnit::Nanoseconds,
+            TimeUnit::Microseconds,
+            TimeUnit::Milliseconds,
+        ]
+        .iter()
+        .copied()
+        .find(|unit| unit.conversion_ratio() == ratio)
+    }
+}
+
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TimeUnit::Nanoseconds => "ns",
+            TimeUnit::Microseconds => "µs",
+            TimeUnit::Milliseconds => "ms",
+        })
+    }
+}
+
+/// A [`Histogram`](struct.Histogram.html) which records `Duration`s as whole numbers of a
+/// [`TimeUnit`](enum.TimeUnit.html).
+///
+/// Recorded durations are rounded down to the unit, and reported values are converted back to
+/// `Duration`s. The unit is stored in the encoded histogram's header, as the conversion ratio from
+/// values to seconds, so it survives `encode` and `decode`.
+///
+/// ```
+/// # use hdrhistogram_c::{DurationHistogram, TimeUnit};
+/// # use std::time::Duration;
+/// let mut h = DurationHistogram::new(
+///     TimeUnit::Microseconds,
+///     Duration::from_micros(1),
+///     Duration::from_secs(60),
+///     3,
+/// )
+/// .unwrap();
+///
+/// h.record(Duration::from_millis(2));
+/// h.record(Duration::from_nanos(3500));
+///
+/// assert_eq!(h.min(), Duration::from_micros(3));
+/// assert_eq!(h.max(), Duration::from_millis(2));
+///
+/// # #[cfg(feature = "hdr_log")]
+/// # {
+/// let decoded = DurationHistogram::decode(&h.encode().unwrap()).unwrap();
+/// assert_eq!(decoded.unit(), TimeUnit::Microseconds);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DurationHistogram {
+    histogram: Histogram,
+    unit: TimeUnit,
+}
+
+impl DurationHistogram {
+    /// Create a histogram of durations in `unit`, with the same parameters as `Histogram::new`.
+    /// The lowest discernible value must be at least one `unit`.
+    pub fn new(
+        unit: TimeUnit,
+        lowest_discernible_value: Duration,
+        highest_trackable_value: Duration,
+        significant_figures: i32,
+    ) -> Result<Self, HistogramErr> {
+        let histogram = Histogram::new(
+            unit.from_duration(lowest_discernible_value),
+            unit.from_duration(highest_trackable_value),
+            significant_figures,
+        )?;
+
+        Ok(DurationHistogram::from_histogram(histogram, unit))
+    }
+
+    /// Treat the values of `histogram` as being in `unit`.
+    pub fn from_histogram(histogram: Histogram, unit: TimeUnit) -> Self {
+        unsafe { ffi::hdr_rust_set_conversion_ratio(histogram.0, unit.conversion_ratio()) };
+        DurationHistogram { histogram, unit }
+    }
+
+    /// Unit of the recorded values.
+    pub fn unit(&self) -> TimeUnit {
+        self.unit
+    }
+
+    /// The underlying histogram, with values in `unit`.
+    pub fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    /// Return the underlying histogram.
+    pub fn into_histogram(self) -> Histogram {
+        self.histogram
+    }
+
+    /// Record a duration. Returns false if it's out of range.
+    pub fn record(&mut self, duration: Duration) -> bool {
+        self.histogram
+            .record_value(self.unit.from_duration(duration))
+    }
+
+    /// Record multiple counts of a duration. Returns false if it's out of range.
+    pub fn record_n(&mut self, duration: Duration, count: i64) -> bool {
+        self.histogram
+            .record_values(self.unit.from_duration(duration), count)
+    }
+
+    /// Record a duration, returning an error if it's out of range.
+    pub fn try_record(&mut self, duration: Duration) -> Result<(), HistogramErr> {
+        self.histogram
+            .try_record_value(self.unit.from_duration(duration))
+    }
+
+    /// Add the counts of `other`, which must be in the same unit. Returns an error if the units
+    /// differ, or if any counts were out of range and dropped.
+    pub fn add(&mut self, other: &DurationHistogram) -> Result<(), HistogramErr> {
+        if self.unit != other.unit {
+            return Err(HistogramErr::TimeUnitMismatch {
+                expected: self.unit,
+                found: other.unit,
+            });
+        }
+        self.histogram.try_add(&other.histogram)
+    }
+
+    pub fn reset(&mut self) {
+        self.histogram.reset()
+    }
+
+    pub fn total_count(&self) -> i64 {
+        self.histogram.total_count()
+    }
+
+    /// Smallest recorded duration, or zero if nothing has been recorded, like `max`.
+    pub fn min(&self) -> Duration {
+        // The histogram's minimum of an empty histogram is i64::MAX.
+        if self.total_count() == 0 {
+            return Duration::ZERO;
+        }
+        self.unit.to_duration(self.histogram.min())
+    }
+
+    pub fn max(&self) -> Duration {
+        self.unit.to_duration(self.histogram.max())
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.unit.to_duration_f64(self.histogram.mean())
+    }
+
+    pub fn stddev(&self) -> Duration {
+        self.unit.to_duration_f64(self.histogram.stddev())
+    }
+
+    pub fn value_at_percentile(&self, percentile: f64) -> Duration {
+        self.unit
+            .to_duration(self.histogram.value_at_percentile(percentile))
+    }
+
+    /// Count of recorded values equivalent to `duration`.
+    pub fn count_at(&self, duration: Duration) -> i64 {
+        self.histogram
+            .count_at_value(self.unit.from_duration(duration))
+    }
+
+    /// Encode into a Base64 string, as `Histogram::encode`, including the unit.
+    pub fn encode(&self) -> Result<String, HistogramErr> {
+        self.histogram.encode()
+    }
+
+    /// Decode a Base64 string generated by `encode`. Histograms which weren't encoded from a
+    /// `DurationHistogram` have no unit, and fail with `HistogramErr::UnknownTimeUnit`.
+    pub fn decode(base64: &String) -> Result<Self, HistogramErr> {
+        Self::with_encoded_unit(Histogram::decode(base64)?)
+    }
+
+    /// Encode into the binary V2 compressed format, as `Histogram::encode_to_vec`, including the
+    /// unit.
+    pub fn encode_to_vec(&self) -> Result<Vec<u8>, HistogramErr> {
+        self.histogram.encode_to_vec()
+    }
+
+    /// Decode binary data generated by `encode_to_vec`.
+    pub fn decode_from_slice(bytes: &[u8]) -> Result<Self, HistogramErr> {
+        Self::with_encoded_unit(Histogram::decode_from_slice(bytes)?)
+    }
+
+    fn with_encoded_unit(histogram: Histogram) -> Result<Self, HistogramErr> {
+        let ratio = unsafe { ffi::hdr_rust_conversion_ratio(histogram.0) };
+
+        match TimeUnit::from_conversion_ratio(ratio) {
+            Some(unit) => Ok(DurationHistogram { histogram, unit }),
+            None => Err(HistogramErr::UnknownTimeUnit(ratio)),
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Rust implementation of the V2 histogram encoding from `hdr_histogram_log.c`.
+//!
+//! This provides the encoding functions bridged in `lib.rs` with the same names, signatures and
+//! error codes, built on the accessor glue so it works with either backend. It's always used by
+//! the pure Rust backend, and by the C backend with the `rust_deflate` feature, so that encoding
+//! doesn't need zlib. The uncompressed payload matches what `hdr_encode_compressed` writes, but
+//! compression is done by miniz_oxide, whose output isn't byte-identical to zlib's. Either way
+//! the results are standard V2 encodings that decode with any HdrHistogram implementation.
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::{ptr, slice};
+
+use libc::c_char;
+
+use crate::{
+    error::{
+        HDR_COMPRESSION_COOKIE_MISMATCH, HDR_DEFLATE_FAIL, HDR_ENCODED_INPUT_TOO_LONG,
+        HDR_ENCODING_COOKIE_MISMATCH, HDR_INFLATE_FAIL, HDR_TRAILING_ZEROS_INVALID,
+        HDR_VALUE_TRUNCATED,
+    },
+    ffi::{self, hdr_histogram},
+};
+
+#[cfg(not(feature = "rust_deflate"))]
+use crate::error::{HDR_DEFLATE_INIT_FAIL, HDR_INFLATE_INIT_FAIL};
+
+const V2_ENCODING_COOKIE: i32 = 0x1c849303;
+const V2_COMPRESSION_COOKIE: i32 = 0x1c849304;
+
+/// Size of the header preceding the V2 counts payload.
+const ENCODING_HEADER_SIZE: usize = 40;
+
+/// Size of the compressed header: cookie and compressed length.
+const COMPRESSION_HEADER_SIZE: usize = 8;
+
+/// zlib's default, as used by `compress()`.
+#[cfg(feature = "rust_deflate")]
+const COMPRESSION_LEVEL: u8 = 6;
+
+fn get_cookie_base(cookie: i32) -> i32 {
+    cookie & !0xf0
+}
+
+fn zig_zag_encode_i64(buffer: &mut Vec<u8>, signed_value: i64) {
+    let mut value = ((signed_value << 1) ^ (signed_value >> 63)) as u64;
+
+    // 7 bits per byte for the first 8 bytes, then all 8 bits of the 9th.
+    for _ in 0..8 {
+        if value >> 7 == 0 {
+            buffer.push(value as u8);
+            return;
+        }
+        buffer.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+/// Decode one value from `buffer`, returning it and the number of bytes it used. Reads past the
+/// end of `buffer` see zeros, so truncation can be detected from the returned length.
+fn zig_zag_decode_i64(buffer: &[u8]) -> (i64, usize) {
+    let byte = |i: usize| buffer.get(i).copied().unwrap_or(0) as u64;
+
+    let mut value = 0u64;
+    let mut bytes_read = 0;
+    loop {
+        let v = byte(bytes_read);
+        if bytes_read == 8 {
+            value |= v << 56;
+            bytes_read += 1;
+            break;
+        }
+
+        value |= (v & 0x7f) << (7 * bytes_read);
+        bytes_read += 1;
+        if v & 0x80 == 0 {
+            break;
+        }
+    }
+
+    (((value >> 1) as i64) ^ -((value & 1) as i64), bytes_read)
+}
+
+/// Encode the uncompressed V2 representation: header, then zig-zag LEB128 counts with runs of
+/// zeros collapsed into a negative count.
+///
+/// As in `hdr_encode_compressed`, the raw counts array is written along with the normalizing
+/// index offset, and every run of zeros, even a single one, is written as its negated length.
+unsafe fn encode_v2(h: *mut hdr_histogram) -> Vec<u8> {
+    // Counts stop at the index of the maximum value, which is that of the last non-zero count.
+    // An empty histogram still encodes its first count.
+    let counts_len = ffi::hdr_rust_counts_len(h) as i32;
+    let counts_limit = (0..counts_len)
+        .rev()
+        .find(|&i| ffi::hdr_count_at_index(h, i) != 0)
+        .map_or(1, |i| i + 1)
+        .min(counts_len);
+
+    let mut counts = Vec::new();
+    let mut i = 0;
+    while i < counts_limit {
+        let value = ffi::hdr_rust_get_count_at_index(h, i);
+        i += 1;
+
+        if value == 0 {
+            let mut zeros = 1;
+            while i < counts_limit && ffi::hdr_rust_get_count_at_index(h, i) == 0 {
+                zeros += 1;
+                i += 1;
+            }
+
+            zig_zag_encode_i64(&mut counts, -zeros);
+        } else {
+            zig_zag_encode_i64(&mut counts, value);
+        }
+    }
+
+    let mut encoded = Vec::with_capacity(ENCODING_HEADER_SIZE + counts.len());
+    encoded.extend_from_slice(&(V2_ENCODING_COOKIE | 0x10).to_be_bytes());
+    encoded.extend_from_slice(&(counts.len() as i32).to_be_bytes());
+    encoded.extend_from_slice(&ffi::hdr_rust_normalizing_index_offset(h).to_be_bytes());
+    encoded.extend_from_slice(&ffi::hdr_rust_significant_figures(h).to_be_bytes());
+    encoded.extend_from_slice(&ffi::hdr_rust_lowest_discernible_value(h).to_be_bytes());
+    encoded.extend_from_slice(&ffi::hdr_rust_highest_trackable_value(h).to_be_bytes());
+    encoded.extend_from_slice(&ffi::hdr_rust_conversion_ratio(h).to_bits().to_be_bytes());
+    encoded.extend_from_slice(&counts);
+
+    encoded
+}
+
+fn be32(buffer: &[u8], at: usize) -> i32 {
+    i32::from_be_bytes([buffer[at], buffer[at + 1], buffer[at + 2], buffer[at + 3]])
+}
+
+fn be64(buffer: &[u8], at: usize) -> i64 {
+    (be32(buffer, at) as i64) << 32 | (be32(buffer, at + 4) as u32 as i64)
+}
+
+/// Decode the uncompressed V2 representation into a newly allocated histogram.
+unsafe fn decode_v2(encoded: &[u8]) -> Result<*mut hdr_histogram, i32> {
+    if encoded.len() < ENCODING_HEADER_SIZE {
+        return Err(HDR_INFLATE_FAIL);
+    }
+
+    let be32 = |at: usize| be32(encoded, at);
+    let be64 = |at: usize| be64(encoded, at);
+
+    if get_cookie_base(be32(0)) != V2_ENCODING_COOKIE {
+        return Err(HDR_ENCODING_COOKIE_MISMATCH);
+    }
+
+    let payload_len = be32(4);
+    let normalizing_index_offset = be32(8);
+    let significant_figures = be32(12);
+    let lowest_discernible_value = be64(16);
+    let highest_trackable_value = be64(24);
+    let conversion_ratio = f64::from_bits(be64(32) as u64);
+
+    let payload = &encoded[ENCODING_HEADER_SIZE..];
+    if payload_len < 0 || payload.len() < payload_len as usize {
+        return Err(HDR_INFLATE_FAIL);
+    }
+    let payload = &payload[..payload_len as usize];
+
+    let mut h = ptr::null_mut();
+    let r = ffi::hdr_init(
+        lowest_discernible_value,
+        highest_trackable_value,
+        significant_figures,
+        &mut h,
+    );
+    if r != 0 {
+        return Err(r);
+    }
+
+    if let Err(err) = decode_counts(h, payload) {
+        ffi::hdr_close(h);
+        return Err(err);
+    }
+
+    ffi::hdr_rust_set_normalizing_index_offset(h, normalizing_index_offset);
+    ffi::hdr_rust_set_conversion_ratio(h, conversion_ratio);
+    ffi::hdr_rust_reset_internal_counters(h);
+
+    Ok(h)
+}
+
+/// Fill the counts of the freshly allocated `h` from the V2 counts payload.
+unsafe fn decode_counts(h: *mut hdr_histogram, payload: &[u8]) -> Result<(), i32> {
+    let counts_len = ffi::hdr_rust_counts_len(h) as i32;
+
+    let mut data_index = 0;
+    let mut counts_index = 0;
+    while data_index < payload.len() && counts_index < counts_len {
+        let (value, bytes_read) = zig_zag_decode_i64(&payload[data_index..]);
+        data_index += bytes_read;
+
+        if value < 0 {
+            let zeros = -value;
+            if value <= i32::MIN as i64 || counts_index as i64 + zeros > counts_len as i64 {
+                return Err(HDR_TRAILING_ZEROS_INVALID);
+            }
+            counts_index += zeros as i32;
+        } else {
+            ffi::hdr_rust_set_count_at_index(h, counts_index, value);
+            counts_index += 1;
+        }
+    }
+
+    if data_index > payload.len() {
+        Err(HDR_VALUE_TRUNCATED)
+    } else if data_index < payload.len() {
+        Err(HDR_ENCODED_INPUT_TOO_LONG)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rust_deflate")]
+fn compress(encoded: &[u8]) -> Result<Vec<u8>, i32> {
+    Ok(miniz_oxide::deflate::compress_to_vec_zlib(
+        encoded,
+        COMPRESSION_LEVEL,
+    ))
+}
+
+#[cfg(feature = "rust_deflate")]
+fn decompress(compressed: &[u8]) -> Result<Vec<u8>, i32> {
+    use miniz_oxide::inflate::{decompress_to_vec_zlib_with_limit, TINFLStatus};
+
+    // Inflate just the header first, then bound the rest by its payload length, so a small
+    // hostile input can't inflate into an arbitrarily large buffer.
+    let header = match decompress_to_vec_zlib_with_limit(compressed, ENCODING_HEADER_SIZE) {
+        Ok(encoded) => return Ok(encoded),
+        Err(err) if err.status == TINFLStatus::HasMoreOutput => err.output,
+        Err(_) => return Err(HDR_INFLATE_FAIL),
+    };
+
+    let limit = ENCODING_HEADER_SIZE + payload_len(&header)?;
+    decompress_to_vec_zlib_with_limit(compressed, limit).map_err(|_| HDR_INFLATE_FAIL)
+}
+
+/// Payload length from a V2 `header`, checked against the most the counts of the histogram it
+/// describes can encode to.
+#[cfg(feature = "rust_deflate")]
+fn payload_len(header: &[u8]) -> Result<usize, i32> {
+    if get_cookie_base(be32(header, 0)) != V2_ENCODING_COOKIE {
+        return Err(HDR_ENCODING_COOKIE_MISMATCH);
+    }
+
+    let (mut unit_magnitude, mut sub_bucket_count, mut bucket_count, mut counts_len) = (0, 0, 0, 0);
+    let r = unsafe {
+        ffi::hdr_rust_calculate_bucket_config(
+            be64(header, 16),
+            be64(header, 24),
+            be32(header, 12),
+            &mut unit_magnitude,
+            &mut sub_bucket_count,
+            &mut bucket_count,
+            &mut counts_len,
+        )
+    };
+    if r != 0 {
+        return Err(r);
+    }
+
+    // Each count is at most 9 bytes of zig-zag LEB128.
+    let payload_len = be32(header, 4);
+    if payload_len < 0 || payload_len as usize > counts_len as usize * 9 {
+        return Err(HDR_ENCODED_INPUT_TOO_LONG);
+    }
+
+    Ok(payload_len as usize)
+}
+
+// Without `rust_deflate`, encoding and decoding fail, as with `hdr_histogram_log_no_op.c`.
+#[cfg(not(feature = "rust_deflate"))]
+fn compress(_encoded: &[u8]) -> Result<Vec<u8>, i32> {
+    Err(HDR_DEFLATE_INIT_FAIL)
+}
+
+#[cfg(not(feature = "rust_deflate"))]
+fn decompress(_compressed: &[u8]) -> Result<Vec<u8>, i32> {
+    Err(HDR_INFLATE_INIT_FAIL)
+}
+
+/// Copy `bytes` into a `malloc` allocation, with `nul` extra zero bytes.
+unsafe fn malloc_copy(bytes: &[u8], nul: usize) -> *mut u8 {
+    let p = libc::malloc(bytes.len() + nul) as *mut u8;
+    if !p.is_null() {
+        ptr::copy_nonoverlapping(bytes.as_ptr(), p, bytes.len());
+        ptr::write_bytes(p.add(bytes.len()), 0, nul);
+    }
+    p
+}
+
+unsafe fn encode_compressed(h: *mut hdr_histogram) -> Result<Vec<u8>, i32> {
+    let compressed = compress(&encode_v2(h)).map_err(|_| HDR_DEFLATE_FAIL)?;
+
+    let mut buffer = Vec::with_capacity(COMPRESSION_HEADER_SIZE + compressed.len());
+    buffer.extend_from_slice(&(V2_COMPRESSION_COOKIE | 0x10).to_be_bytes());
+    buffer.extend_from_slice(&(compressed.len() as i32).to_be_bytes());
+    buffer.extend_from_slice(&compressed);
+
+    Ok(buffer)
+}
+
+unsafe fn decode_compressed(buffer: &[u8]) -> Result<*mut hdr_histogram, i32> {
+    if buffer.len() < COMPRESSION_HEADER_SIZE {
+        return Err(libc::EINVAL);
+    }
+
+    let cookie = i32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+    if get_cookie_base(cookie) != V2_COMPRESSION_COOKIE {
+        return Err(HDR_COMPRESSION_COOKIE_MISMATCH);
+    }
+
+    let compressed_length = i32::from_be_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]);
+    if compressed_length < 0 || compressed_length as usize > buffer.len() - COMPRESSION_HEADER_SIZE
+    {
+        return Err(libc::EINVAL);
+    }
+
+    let compressed =
+        &buffer[COMPRESSION_HEADER_SIZE..COMPRESSION_HEADER_SIZE + compressed_length as usize];
+    decode_v2(&decompress(compressed)?)
+}
+
+pub unsafe fn hdr_encode_compressed(
+    hdr: *mut hdr_histogram,
+    compressed_histogram: *mut *mut u8,
+    compressed_len: *mut usize,
+) -> i32 {
+    match encode_compressed(hdr) {
+        Ok(buffer) => {
+            let p = malloc_copy(&buffer, 0);
+            if p.is_null() {
+                return libc::ENOMEM;
+            }
+
+            *compressed_histogram = p;
+            *compressed_len = buffer.len();
+            0
+        }
+        Err(err) => err,
+    }
+}
+
+pub unsafe fn hdr_decode_compressed(
+    buffer: *mut u8,
+    length: usize,
+    hdr: *mut *mut hdr_histogram,
+) -> i32 {
+    match decode_compressed(slice::from_raw_parts(buffer, length)) {
+        Ok(h) => {
+            *hdr = h;
+            0
+        }
+        Err(err) => err,
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len().div_ceil(3) * 4);
+
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize;
+
+        output.push(BASE64[n >> 18]);
+        output.push(BASE64[(n >> 12) & 0x3f]);
+        output.push(if chunk.len() > 1 {
+            BASE64[(n >> 6) & 0x3f]
+        } else {
+            b'='
+        });
+        output.push(if chunk.len() > 2 {
+            BASE64[n & 0x3f]
+        } else {
+            b'='
+        });
+    }
+
+    output
+}
+
+/// Decode Base64, which must be a multiple of 4 characters. As in the C implementation, padding
+/// decodes as zero bits rather than shortening the output.
+fn base64_decode(input: &[u8]) -> Result<Vec<u8>, i32> {
+    let chunks = input.chunks_exact(4);
+    if !chunks.remainder().is_empty() {
+        return Err(libc::EINVAL);
+    }
+
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Ok(c - b'A'),
+        b'a'..=b'z' => Ok(c - b'a' + 26),
+        b'0'..=b'9' => Ok(c - b'0' + 52),
+        b'+' => Ok(62),
+        b'/' => Ok(63),
+        b'=' => Ok(0),
+        _ => Err(libc::EINVAL),
+    };
+
+    let mut output = Vec::with_capacity(input.len() / 4 * 3);
+    for chunk in chunks {
+        let mut n = 0u32;
+        for &c in chunk {
+            n = n << 6 | sextet(c)? as u32;
+        }
+        output.extend_from_slice(&n.to_be_bytes()[1..]);
+    }
+
+    Ok(output)
+}
+
+pub unsafe fn hdr_log_encode(hdr: *mut hdr_histogram, encoded: *mut *mut c_char) -> i32 {
+    match encode_compressed(hdr) {
+        Ok(buffer) => {
+            let p = malloc_copy(&base64_encode(&buffer), 1);
+            if p.is_null() {
+                return libc::ENOMEM;
+            }
+
+            *encoded = p as *mut c_char;
+            0
+        }
+        Err(err) => err,
+    }
+}
+
+pub unsafe fn hdr_log_decode(
+    hdr: *mut *mut hdr_histogram,
+    base64_histogram: *mut c_char,
+    base64_len: usize,
+) -> i32 {
+    let base64 = slice::from_raw_parts(base64_histogram as *const u8, base64_len);
+
+    match base64_decode(base64).and_then(|buffer| decode_compressed(&buffer)) {
+        Ok(h) => {
+            *hdr = h;
+            0
+        }
+        Err(err) => err,
+    }
+}
@@ -0,0 +1,197 @@
+//! Error type.
+
+use std::{ffi::CStr, io, str};
+
+use thiserror::Error;
+
+use crate::{ffi, TimeUnit};
+
+// Error codes from `enum hdr_log_errors` in hdr_histogram_log.h
+pub(crate) const HDR_COMPRESSION_COOKIE_MISMATCH: i32 = -29999;
+pub(crate) const HDR_ENCODING_COOKIE_MISMATCH: i32 = -29998;
+pub(crate) const HDR_DEFLATE_INIT_FAIL: i32 = -29997;
+pub(crate) const HDR_DEFLATE_FAIL: i32 = -29996;
+pub(crate) const HDR_INFLATE_INIT_FAIL: i32 = -29995;
+pub(crate) const HDR_INFLATE_FAIL: i32 = -29994;
+pub(crate) const HDR_LOG_INVALID_VERSION: i32 = -29993;
+pub(crate) const HDR_TRAILING_ZEROS_INVALID: i32 = -29992;
+pub(crate) const HDR_VALUE_TRUNCATED: i32 = -29991;
+pub(crate) const HDR_ENCODED_INPUT_TOO_LONG: i32 = -29990;
+
+/// Errors from creating, recording into, encoding or decoding histograms.
+#[derive(Error, Debug)]
+pub enum HistogramErr {
+    /// The lowest discernible value must be at least 1.
+    #[error("Lowest discernible value {} must be >= 1", _0)]
+    InvalidLowestDiscernibleValue(i64),
+    /// The highest trackable value must be at least twice the lowest discernible value.
+    #[error(
+        "Highest trackable value {} must be >= 2 * lowest discernible value {}",
+        highest,
+        lowest
+    )]
+    InvalidHighestTrackableValue { lowest: i64, highest: i64 },
+    /// Significant figures must be in the range 1..=5.
+    #[error("Significant figures {} must be in 1..=5", _0)]
+    InvalidSignificantFigures(i32),
+    /// Memory allocation failed.
+    #[error("Allocation failed")]
+    AllocFail,
+    /// Initialization failed for some other reason.
+    #[error("Initialization failed")]
+    InitFailed,
+    /// A value was outside the range the histogram can track.
+    #[error("Value {} is outside the histogram's trackable range", _0)]
+    ValueOutOfRange(i64),
+    /// Adding a histogram dropped counts which were out of range.
+    #[error("{} counts were out of range and dropped", _0)]
+    CountsDropped(i64),
+    /// Histograms with different bucket layouts can't be combined bucket by bucket.
+    #[error("Histograms have incompatible bucket layouts")]
+    IncompatibleHistograms,
+    /// Subtracting would have left a negative count.
+    #[error("Subtraction would make the count at value {} negative", _0)]
+    NegativeCount(i64),
+    /// Encoded data didn't start with the V2 compression cookie.
+    #[error("Bad compression cookie {:#010x}", _0)]
+    BadCookie(u32),
+    /// Encoded data was too short to hold even the compression cookie.
+    #[error("Encoded histogram is truncated")]
+    Truncated,
+    /// Encoded data used an encoding or log format version which isn't supported.
+    #[error("Unsupported encoding")]
+    UnsupportedEncoding,
+    /// Compressing the encoded histogram failed.
+    #[error("Deflate failed")]
+    DeflateFailed,
+    /// Decompressing the encoded histogram failed.
+    #[error("Inflate failed")]
+    InflateFailed,
+    /// The encoded counts don't fit in the histogram described by the encoded header.
+    #[error("Encoded counts too long for destination histogram")]
+    TooSmallDestination,
+    /// The encoded histogram is malformed.
+    #[error("Invalid encoded histogram")]
+    InvalidHistogram,
+    /// Encoding or decoding failed for some other reason.
+    #[error("Encoding/Decoding failed: {}", _0)]
+    CodecFailed(&'static str),
+    /// Shifting values would have taken them out of range or lost precision.
+    #[error(
+        "Shifting values by {} binary orders of magnitude overflows or loses precision",
+        _0
+    )]
+    ShiftOutOfRange(u32),
+    /// Values can only be rescaled by a finite, positive factor.
+    #[error("Invalid scale factor {}", _0)]
+    InvalidScaleFactor(f64),
+    /// Reducing precision can't add significant figures the histogram doesn't have.
+    #[error(
+        "Can't increase precision from {} to {} significant figures",
+        current,
+        requested
+    )]
+    CannotIncreasePrecision { current: i32, requested: i32 },
+    /// A decoded histogram's conversion ratio isn't that of a `TimeUnit`.
+    #[error("Conversion ratio {} doesn't identify a time unit", _0)]
+    UnknownTimeUnit(f64),
+    /// Histograms of durations in different units were combined.
+    #[error("Time unit mismatch: expected {}, found {}", expected, found)]
+    TimeUnitMismatch { expected: TimeUnit, found: TimeUnit },
+    /// SLO targets are percentages strictly between 0 and 100.
+    #[error("Invalid SLO target {}", _0)]
+    InvalidSloTarget(f64),
+    /// Reading or writing failed.
+    #[error("I/O error: {}", _0)]
+    Io(#[from] io::Error),
+    /// Interval log tags may not be empty, or contain commas or whitespace.
+    #[error("Invalid log tag: {:?}", _0)]
+    InvalidTag(String),
+    /// An interval log line couldn't be parsed.
+    #[error("Interval log line {}: {}", line, reason)]
+    LogFormat { line: usize, reason: String },
+}
+
+impl HistogramErr {
+    /// Check histogram parameters, with the same rules as `hdr_calculate_bucket_config`.
+    pub(crate) fn check_params(
+        lowest_discernible_value: i64,
+        highest_trackable_value: i64,
+        significant_figures: i32,
+    ) -> Result<(), HistogramErr> {
+        if lowest_discernible_value < 1 {
+            return Err(HistogramErr::InvalidLowestDiscernibleValue(
+                lowest_discernible_value,
+            ));
+        }
+        if !(1..=5).contains(&significant_figures) {
+            return Err(HistogramErr::InvalidSignificantFigures(significant_figures));
+        }
+        if lowest_discernible_value.saturating_mul(2) > highest_trackable_value {
+            return Err(HistogramErr::InvalidHighestTrackableValue {
+                lowest: lowest_discernible_value,
+                highest: highest_trackable_value,
+            });
+        }
+        Ok(())
+    }
+
+    /// Error from a failed `hdr_*_init` call.
+    pub(crate) fn init(res: i32) -> HistogramErr {
+        if res == libc::ENOMEM {
+            HistogramErr::AllocFail
+        } else {
+            HistogramErr::InitFailed
+        }
+    }
+
+    /// Error from a failed encode or decode. `cookie` returns the cookie of the input data, and is
+    /// only called if the failure was a cookie mismatch.
+    pub(crate) fn codec(res: i32, cookie: impl FnOnce() -> u32) -> HistogramErr {
+        match res {
+            HDR_COMPRESSION_COOKIE_MISMATCH => HistogramErr::BadCookie(cookie()),
+            HDR_ENCODING_COOKIE_MISMATCH | HDR_LOG_INVALID_VERSION => {
+                HistogramErr::UnsupportedEncoding
+            }
+            HDR_DEFLATE_INIT_FAIL | HDR_DEFLATE_FAIL => HistogramErr::DeflateFailed,
+            HDR_INFLATE_INIT_FAIL | HDR_INFLATE_FAIL => HistogramErr::InflateFailed,
+            HDR_ENCODED_INPUT_TOO_LONG => HistogramErr::TooSmallDestination,
+            HDR_TRAILING_ZEROS_INVALID | HDR_VALUE_TRUNCATED | libc::EINVAL => {
+                HistogramErr::InvalidHistogram
+            }
+            libc::ENOMEM => HistogramErr::AllocFail,
+            _ => HistogramErr::CodecFailed(
+                str::from_utf8(unsafe { CStr::from_ptr(ffi::hdr_strerror(res)) }.to_bytes())
+                    .unwrap(),
+            ),
+        }
+    }
+}
+
+/// Big-endian cookie at the start of binary encoded data, if it's long enough to hold one.
+pub(crate) fn binary_cookie(bytes: &[u8]) -> Option<u32> {
+    let cookie = bytes.get(..4)?;
+    Some(u32::from_be_bytes([
+        cookie[0], cookie[1], cookie[2], cookie[3],
+    ]))
+}
+
+/// Cookie at the start of Base64 encoded data, if it's long enough to hold one. 6 Base64
+/// characters cover the 4 cookie bytes.
+pub(crate) fn base64_cookie(base64: &str) -> Option<u32> {
+    let bits = base64
+        .as_bytes()
+        .get(..6)?
+        .iter()
+        .map(|&c| match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => 0,
+        })
+        .fold(0u64, |acc, sextet| (acc << 6) | sextet as u64);
+
+    Some((bits >> 4) as u32)
+}
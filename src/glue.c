@@ -1,6 +1,12 @@
 #include "glue.h"
 #include <stdlib.h>
+#ifdef HDR_RUST_SYSTEM_LIB
+/* hdr_malloc.h isn't installed, and a system library uses the default allocator */
+#define hdr_calloc calloc
+#define hdr_free free
+#else
 #include <hdr_malloc.h>
+#endif
 #include <string.h>
 
 int64_t hdr_rust_total_count(const struct hdr_histogram *h)
@@ -13,6 +19,130 @@ int64_t hdr_rust_counts_len(const struct hdr_histogram *h)
     return h->counts_len;
 }
 
+int64_t hdr_rust_lowest_discernible_value(const struct hdr_histogram *h)
+{
+    return h->lowest_discernible_value;
+}
+
+int64_t hdr_rust_highest_trackable_value(const struct hdr_histogram *h)
+{
+    return h->highest_trackable_value;
+}
+
+int32_t hdr_rust_significant_figures(const struct hdr_histogram *h)
+{
+    return h->significant_figures;
+}
+
+int32_t hdr_rust_unit_magnitude(const struct hdr_histogram *h)
+{
+    return h->unit_magnitude;
+}
+
+int32_t hdr_rust_sub_bucket_count(const struct hdr_histogram *h)
+{
+    return h->sub_bucket_count;
+}
+
+int32_t hdr_rust_bucket_count(const struct hdr_histogram *h)
+{
+    return h->bucket_count;
+}
+
+int32_t hdr_rust_normalizing_index_offset(const struct hdr_histogram *h)
+{
+    return h->normalizing_index_offset;
+}
+
+double hdr_rust_conversion_ratio(const struct hdr_histogram *h)
+{
+    return h->conversion_ratio;
+}
+
+void hdr_rust_set_normalizing_index_offset(struct hdr_histogram *h, int32_t offset)
+{
+    h->normalizing_index_offset = offset;
+}
+
+void hdr_rust_set_conversion_ratio(struct hdr_histogram *h, double ratio)
+{
+    h->conversion_ratio = ratio;
+}
+
+/* Indexes the counts array directly, without applying normalizing_index_offset */
+int64_t hdr_rust_get_count_at_index(const struct hdr_histogram *h, int32_t index)
+{
+    return h->counts[index];
+}
+
+/* Doesn't update total_count, min_value or max_value; see hdr_rust_reset_internal_counters() */
+void hdr_rust_set_count_at_index(struct hdr_histogram *h, int32_t index, int64_t count)
+{
+    h->counts[index] = count;
+}
+
+int hdr_rust_calculate_bucket_config(int64_t lowest_discernible_value,
+                                     int64_t highest_trackable_value,
+                                     int significant_figures,
+                                     int32_t *unit_magnitude,
+                                     int32_t *sub_bucket_count,
+                                     int32_t *bucket_count,
+                                     int32_t *counts_len)
+{
+    struct hdr_histogram_bucket_config cfg;
+    int r;
+
+    r = hdr_calculate_bucket_config(lowest_discernible_value, highest_trackable_value,
+                                    significant_figures, &cfg);
+    if (r)
+    {
+        return r;
+    }
+
+    *unit_magnitude = (int32_t)cfg.unit_magnitude;
+    *sub_bucket_count = cfg.sub_bucket_count;
+    *bucket_count = cfg.bucket_count;
+    *counts_len = cfg.counts_len;
+
+    return 0;
+}
+
+/* Matches hdr_get_memory_size() */
+size_t hdr_rust_memory_size(int32_t counts_len)
+{
+    return sizeof(struct hdr_histogram) + (size_t)counts_len * sizeof(int64_t);
+}
+
+/* Allocate a copy of h with a new highest trackable value. The counts layout only depends on
+ * the lowest discernible value and significant figures, so each count keeps its index. */
+int hdr_rust_resize(const struct hdr_histogram *h, int64_t highest_trackable_value,
+                    struct hdr_histogram **result)
+{
+    struct hdr_histogram *histogram;
+    int32_t i;
+    int r;
+
+    r = hdr_init(h->lowest_discernible_value, highest_trackable_value, h->significant_figures,
+                 &histogram);
+    if (r)
+    {
+        return r;
+    }
+
+    for (i = 0; i < h->counts_len && i < histogram->counts_len; i++)
+    {
+        histogram->counts[i] = hdr_count_at_index(h, i);
+    }
+    histogram->total_count = h->total_count;
+    histogram->min_value = h->min_value;
+    histogram->max_value = h->max_value;
+    histogram->conversion_ratio = h->conversion_ratio;
+
+    *result = histogram;
+
+    return 0;
+}
+
 struct hdr_histogram *hdr_rust_clone(const struct hdr_histogram *h)
 {
     struct hdr_histogram *histogram;
@@ -37,3 +167,143 @@ struct hdr_histogram *hdr_rust_clone(const struct hdr_histogram *h)
 
     return histogram;
 }
+
+/* Recompute total_count, min_value and max_value from the counts array */
+void hdr_rust_reset_internal_counters(struct hdr_histogram *h)
+{
+    int32_t min_non_zero_index = -1;
+    int32_t max_index = -1;
+    int64_t total_count = 0;
+    int32_t i;
+
+    for (i = 0; i < h->counts_len; i++)
+    {
+        int64_t count = hdr_count_at_index(h, i);
+
+        if (count > 0)
+        {
+            total_count += count;
+            max_index = i;
+            if (min_non_zero_index == -1 && i != 0)
+            {
+                min_non_zero_index = i;
+            }
+        }
+    }
+
+    h->max_value = max_index == -1 ? 0 : hdr_value_at_index(h, max_index);
+    h->min_value = min_non_zero_index == -1 ? INT64_MAX : hdr_value_at_index(h, min_non_zero_index);
+    h->total_count = total_count;
+}
+
+struct hdr_iter *hdr_rust_iter_alloc(void)
+{
+    return (struct hdr_iter *)hdr_calloc(1, sizeof(struct hdr_iter));
+}
+
+void hdr_rust_iter_free(struct hdr_iter *iter)
+{
+    hdr_free(iter);
+}
+
+int64_t hdr_rust_iter_count(const struct hdr_iter *iter)
+{
+    return iter->count;
+}
+
+int64_t hdr_rust_iter_cumulative_count(const struct hdr_iter *iter)
+{
+    return iter->cumulative_count;
+}
+
+int64_t hdr_rust_iter_value(const struct hdr_iter *iter)
+{
+    return iter->value;
+}
+
+int64_t hdr_rust_iter_highest_equivalent_value(const struct hdr_iter *iter)
+{
+    return iter->highest_equivalent_value;
+}
+
+int64_t hdr_rust_iter_lowest_equivalent_value(const struct hdr_iter *iter)
+{
+    return iter->lowest_equivalent_value;
+}
+
+int64_t hdr_rust_iter_median_equivalent_value(const struct hdr_iter *iter)
+{
+    return iter->median_equivalent_value;
+}
+
+int64_t hdr_rust_iter_value_iterated_from(const struct hdr_iter *iter)
+{
+    return iter->value_iterated_from;
+}
+
+int64_t hdr_rust_iter_value_iterated_to(const struct hdr_iter *iter)
+{
+    return iter->value_iterated_to;
+}
+
+int64_t hdr_rust_iter_recorded_count_added(const struct hdr_iter *iter)
+{
+    return iter->specifics.recorded.count_added_in_this_iteration_step;
+}
+
+int64_t hdr_rust_iter_linear_count_added(const struct hdr_iter *iter)
+{
+    return iter->specifics.linear.count_added_in_this_iteration_step;
+}
+
+int64_t hdr_rust_iter_log_count_added(const struct hdr_iter *iter)
+{
+    return iter->specifics.log.count_added_in_this_iteration_step;
+}
+
+double hdr_rust_iter_percentile(const struct hdr_iter *iter)
+{
+    return iter->specifics.percentiles.percentile;
+}
+
+struct hdr_interval_recorder *hdr_rust_interval_recorder_alloc(void)
+{
+    return (struct hdr_interval_recorder *)hdr_calloc(1, sizeof(struct hdr_interval_recorder));
+}
+
+/* As hdr_interval_recorder_init_all(), but if allocating the active histogram fails, destroys the
+ * phaser rather than leaking its lock */
+int hdr_rust_interval_recorder_init_all(struct hdr_interval_recorder *r,
+                                        int64_t lowest_discernible_value,
+                                        int64_t highest_trackable_value,
+                                        int significant_figures)
+{
+    int rc = hdr_writer_reader_phaser_init(&r->phaser);
+    if (rc != 0)
+    {
+        return rc;
+    }
+
+    rc = hdr_init(lowest_discernible_value, highest_trackable_value, significant_figures, &r->active);
+    if (rc != 0)
+    {
+        hdr_writer_reader_phaser_destroy(&r->phaser);
+    }
+
+    return rc;
+}
+
+void hdr_rust_interval_recorder_free(struct hdr_interval_recorder *r)
+{
+    hdr_free(r);
+}
+
+struct hdr_writer_reader_phaser *hdr_rust_phaser_alloc(void)
+{
+    return (struct hdr_writer_reader_phaser *)hdr_calloc(1, sizeof(struct hdr_writer_reader_phaser));
+}
+
+void hdr_rust_phaser_free(struct hdr_writer_reader_phaser *p)
+{
+    hdr_free(p);
+}
@@ -1,5 +1,8 @@
 #include <stdint.h>
 #include <hdr_histogram.h>
+#include <hdr_histogram_log.h>
+#include <hdr_interval_recorder.h>
+#include <hdr_writer_reader_phaser.h>
 
 #ifdef __cplusplus
 extern "C" {
@@ -7,7 +10,55 @@ extern "C" {
 
 extern int64_t hdr_rust_total_count(const struct hdr_histogram *h);
 extern int64_t hdr_rust_counts_len(const struct hdr_histogram *h);
+extern int64_t hdr_rust_lowest_discernible_value(const struct hdr_histogram *h);
+extern int64_t hdr_rust_highest_trackable_value(const struct hdr_histogram *h);
+extern int32_t hdr_rust_significant_figures(const struct hdr_histogram *h);
+extern int32_t hdr_rust_unit_magnitude(const struct hdr_histogram *h);
+extern int32_t hdr_rust_sub_bucket_count(const struct hdr_histogram *h);
+extern int32_t hdr_rust_bucket_count(const struct hdr_histogram *h);
+extern int32_t hdr_rust_normalizing_index_offset(const struct hdr_histogram *h);
+extern double hdr_rust_conversion_ratio(const struct hdr_histogram *h);
+extern void hdr_rust_set_normalizing_index_offset(struct hdr_histogram *h, int32_t offset);
+extern void hdr_rust_set_conversion_ratio(struct hdr_histogram *h, double ratio);
+extern int64_t hdr_rust_get_count_at_index(const struct hdr_histogram *h, int32_t index);
+extern void hdr_rust_set_count_at_index(struct hdr_histogram *h, int32_t index, int64_t count);
+extern int hdr_rust_calculate_bucket_config(int64_t lowest_discernible_value,
+                                            int64_t highest_trackable_value,
+                                            int significant_figures,
+                                            int32_t *unit_magnitude,
+                                            int32_t *sub_bucket_count,
+                                            int32_t *bucket_count,
+                                            int32_t *counts_len);
+extern size_t hdr_rust_memory_size(int32_t counts_len);
+extern int hdr_rust_resize(const struct hdr_histogram *h, int64_t highest_trackable_value,
+                           struct hdr_histogram **result);
 extern struct hdr_histogram *hdr_rust_clone(const struct hdr_histogram *h);
+extern void hdr_rust_reset_internal_counters(struct hdr_histogram *h);
+
+extern struct hdr_iter *hdr_rust_iter_alloc(void);
+extern void hdr_rust_iter_free(struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_count(const struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_cumulative_count(const struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_value(const struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_highest_equivalent_value(const struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_lowest_equivalent_value(const struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_median_equivalent_value(const struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_value_iterated_from(const struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_value_iterated_to(const struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_recorded_count_added(const struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_linear_count_added(const struct hdr_iter *iter);
+extern int64_t hdr_rust_iter_log_count_added(const struct hdr_iter *iter);
+extern double hdr_rust_iter_percentile(const struct hdr_iter *iter);
+
+extern struct hdr_interval_recorder *hdr_rust_interval_recorder_alloc(void);
+extern int hdr_rust_interval_recorder_init_all(struct hdr_interval_recorder *r,
+                                               int64_t lowest_discernible_value,
+                                               int64_t highest_trackable_value,
+                                               int significant_figures);
+extern void hdr_rust_interval_recorder_free(struct hdr_interval_recorder *r);
+
+extern struct hdr_writer_reader_phaser *hdr_rust_phaser_alloc(void);
+extern void hdr_rust_phaser_free(struct hdr_writer_reader_phaser *p);
 
 #ifdef __cplusplus
 }
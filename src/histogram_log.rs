@@ -0,0 +1,425 @@
+//! HdrHistogram interval log ("hlog") support.
+//!
+//! The format is the one used by HdrHistogram's `HistogramLogWriter` and `HistogramLogReader`, and
+//! understood by tools such as HistogramLogAnalyzer:
+//!
+//! ```text
+//! #[Histogram log format version 1.3]
+//! #[StartTime: 1441812123.120 (seconds since epoch), 2015-09-09T15:22:03.120Z]
+//! #[BaseTime: 1441812123.120 (seconds since epoch)]
+//! "StartTimestamp","Interval_Length","Interval_Max","Interval_Compressed_Histogram"
+//! Tag=foo,0.127,1.007,2.769,HISTFAAAAEV42pNpmSz...
+//! ```
+
+use std::{
+    io::{BufRead, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Histogram, HistogramErr};
+
+const LOG_FORMAT_VERSION: &str = "1.3";
+const LEGEND: &str =
+    "\"StartTimestamp\",\"Interval_Length\",\"Interval_Max\",\"Interval_Compressed_Histogram\"";
+
+/// Writes interval histograms to an interval log.
+///
+/// ```
+/// # use hdrhistogram_c::{Histogram, LogWriter};
+/// use std::time::{Duration, SystemTime};
+///
+/// # #[cfg(feature = "hdr_log")]
+/// # {
+/// let mut h = Histogram::new(1, 1000000, 3).unwrap();
+/// h.record_value(1000);
+///
+/// let start = SystemTime::now();
+/// let mut log = LogWriter::new(Vec::new());
+/// log.write_header(start).unwrap();
+/// log.write_histogram(start, Duration::from_secs(1), &h, Some("web")).unwrap();
+///
+/// let out = String::from_utf8(log.into_inner()).unwrap();
+/// assert!(out.lines().last().unwrap().starts_with("Tag=web,0.000,1.000,0.001,"));
+/// # }
+/// ```
+pub struct LogWriter<W> {
+    writer: W,
+    base_time: Option<SystemTime>,
+    max_value_unit_ratio: f64,
+}
+
+impl<W: Write> LogWriter<W> {
+    /// Create a new `LogWriter`. No header is written until `write_header` (or the individual
+    /// header methods) are called.
+    pub fn new(writer: W) -> Self {
+        LogWriter {
+            writer,
+            base_time: None,
+            max_value_unit_ratio: 1000000.0,
+        }
+    }
+
+    /// Set the ratio the interval max value is divided by when written. Defaults to 1000000.0, as
+    /// with HdrHistogram's Java implementation, which converts nanosecond values to milliseconds.
+    pub fn set_max_value_unit_ratio(&mut self, ratio: f64) {
+        self.max_value_unit_ratio = ratio;
+    }
+
+    /// Write the standard header: the format version, start time, base time and the column
+    /// legend. `start_time` is also used as the base time, so interval timestamps are written
+    /// relative to it.
+    pub fn write_header(&mut self, start_time: SystemTime) -> Result<(), HistogramErr> {
+        self.write_format_version()?;
+        self.write_start_time(start_time)?;
+        self.write_base_time(start_time)?;
+        self.write_legend()
+    }
+
+    /// Write a `#[...]` comment line.
+    pub fn write_comment(&mut self, comment: &str) -> Result<(), HistogramErr> {
+        writeln!(self.writer, "#[{}]", comment)?;
+        Ok(())
+    }
+
+    /// Write the log format version.
+    pub fn write_format_version(&mut self) -> Result<(), HistogramErr> {
+        self.write_comment(&format!(
+            "Histogram log format version {}",
+            LOG_FORMAT_VERSION
+        ))
+    }
+
+    /// Write the `#[StartTime: ...]` header.
+    pub fn write_start_time(&mut self, start_time: SystemTime) -> Result<(), HistogramErr> {
+        let since_epoch = epoch_duration(start_time);
+
+        self.write_comment(&format!(
+            "StartTime: {:.3} (seconds since epoch), {}",
+            since_epoch.as_secs_f64(),
+            format_utc(since_epoch)
+        ))
+    }
+
+    /// Write the `#[BaseTime: ...]` header. Subsequent interval timestamps are written relative to
+    /// `base_time`; without one they're written as seconds since the epoch.
+    pub fn write_base_time(&mut self, base_time: SystemTime) -> Result<(), HistogramErr> {
+        self.base_time = Some(base_time);
+
+        self.write_comment(&format!(
+            "BaseTime: {:.3} (seconds since epoch)",
+            epoch_duration(base_time).as_secs_f64()
+        ))
+    }
+
+    /// Write the column legend.
+    pub fn write_legend(&mut self) -> Result<(), HistogramErr> {
+        writeln!(self.writer, "{}", LEGEND)?;
+        Ok(())
+    }
+
+    /// Write an interval histogram which started at `start_time` and lasted `interval`, with an
+    /// optional tag. Tags may not contain commas or whitespace.
+    pub fn write_histogram(
+        &mut self,
+        start_time: SystemTime,
+        interval: Duration,
+        histogram: &Histogram,
+        tag: Option<&str>,
+    ) -> Result<(), HistogramErr> {
+        let mut line = String::new();
+        if let Some(tag) = tag {
+            if tag.is_empty() || tag.contains(|c: char| c == ',' || c.is_whitespace()) {
+                return Err(HistogramErr::InvalidTag(tag.to_string()));
+            }
+            line = format!("Tag={},", tag);
+        }
+
+        let start = epoch_duration(start_time).as_secs_f64()
+            - self
+                .base_time
+                .map_or(0.0, |base| epoch_duration(base).as_secs_f64());
+
+        // Build the whole line before writing any of it, so a failed encode doesn't leave a
+        // partial line in the log.
+        line += &format!(
+            "{:.3},{:.3},{:.3},{}\n",
+            start,
+            interval.as_secs_f64(),
+            histogram.max() as f64 / self.max_value_unit_ratio,
+            histogram.encode()?
+        );
+        self.writer.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<(), HistogramErr> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// An interval histogram read from an interval log.
+#[derive(Clone)]
+pub struct LogEntry {
+    /// Start of the interval, in seconds since the epoch. If the log's timestamps are relative
+    /// this is resolved against its base or start time.
+    pub start_timestamp: f64,
+    /// Length of the interval, in seconds.
+    pub interval_length: f64,
+    /// The interval's maximum value as written in the log, which is typically scaled (see
+    /// `LogWriter::set_max_value_unit_ratio`). `None` for old logs without the column.
+    pub interval_max: Option<f64>,
+    /// The interval's tag, if any.
+    pub tag: Option<String>,
+    /// The interval histogram.
+    pub histogram: Histogram,
+}
+
+/// Reads interval histograms from an interval log.
+///
+/// This is an iterator over the log's entries, which are read and decoded lazily, one line at a
+/// time. Header lines are interpreted as they're encountered; comments and the legend are skipped.
+/// Errors are reported with the line number they occurred on.
+///
+/// ```
+/// # use hdrhistogram_c::{Histogram, LogReader, LogWriter};
+/// # use std::time::{Duration, SystemTime};
+/// # #[cfg(feature = "hdr_log")]
+/// # {
+/// # let mut h = Histogram::new(1, 1000000, 3).unwrap();
+/// # h.record_value(1000);
+/// # let start = SystemTime::now();
+/// # let mut log = LogWriter::new(Vec::new());
+/// # log.write_header(start).unwrap();
+/// # log.write_histogram(start, Duration::from_secs(1), &h, Some("web")).unwrap();
+/// # log.write_histogram(start, Duration::from_secs(1), &h, None).unwrap();
+/// # let log = log.into_inner();
+/// let reader = LogReader::new(&log[..]).with_tag(Some("web"));
+///
+/// for entry in reader {
+///     let entry = entry.unwrap();
+///     println!("{} {:?} p99={}", entry.start_timestamp, entry.tag,
+///              entry.histogram.value_at_percentile(99.0));
+/// }
+/// # }
+/// ```
+pub struct LogReader<R> {
+    reader: R,
+    line: String,
+    line_number: usize,
+    start_time: Option<f64>,
+    base_time: Option<f64>,
+    time_range: Option<(f64, f64)>,
+    tag: Option<Option<String>>,
+    done: bool,
+}
+
+impl<R: BufRead> LogReader<R> {
+    /// Create a new `LogReader`.
+    pub fn new(reader: R) -> Self {
+        LogReader {
+            reader,
+            line: String::new(),
+            line_number: 0,
+            start_time: None,
+            base_time: None,
+            time_range: None,
+            tag: None,
+            done: false,
+        }
+    }
+
+    /// Only return entries which start within `start..end`, in seconds since the epoch. Entries are
+    /// assumed to be in time order, so reading stops at the first entry starting at or after
+    /// `end`.
+    pub fn with_time_range(mut self, start: f64, end: f64) -> Self {
+        self.time_range = Some((start, end));
+        self
+    }
+
+    /// Only return entries with the given tag, or only untagged entries if `tag` is `None`.
+    pub fn with_tag(mut self, tag: Option<&str>) -> Self {
+        self.tag = Some(tag.map(str::to_string));
+        self
+    }
+
+    /// The log's start time in seconds since the epoch, once its `#[StartTime: ...]` header has
+    /// been read.
+    pub fn start_time(&self) -> Option<f64> {
+        self.start_time
+    }
+
+    /// The log's base time in seconds since the epoch, once its `#[BaseTime: ...]` header has been
+    /// read, or it has been inferred from the first entry.
+    pub fn base_time(&self) -> Option<f64> {
+        self.base_time
+    }
+
+    fn err(&self, reason: impl Into<String>) -> HistogramErr {
+        HistogramErr::LogFormat {
+            line: self.line_number,
+            reason: reason.into(),
+        }
+    }
+
+    fn header_time(&self, line: &str, prefix: &str) -> Result<f64, HistogramErr> {
+        line[prefix.len()..]
+            .split([' ', ']'])
+            .next()
+            .and_then(|t| t.parse().ok())
+            .ok_or_else(|| self.err(format!("Invalid {} header", prefix.trim())))
+    }
+
+    /// Parse the current line. Returns `None` for header lines and entries which are filtered out.
+    fn parse_line(&mut self) -> Result<Option<LogEntry>, HistogramErr> {
+        let line = self.line.trim_end();
+
+        if line.is_empty() || line.starts_with('"') {
+            return Ok(None);
+        }
+        if line.starts_with("#[StartTime: ") {
+            self.start_time = Some(self.header_time(line, "#[StartTime: ")?);
+            return Ok(None);
+        }
+        if line.starts_with("#[BaseTime: ") {
+            self.base_time = Some(self.header_time(line, "#[BaseTime: ")?);
+            return Ok(None);
+        }
+        if line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let (tag, rest) = if let Some(rest) = line.strip_prefix("Tag=") {
+            match rest.find(',') {
+                Some(idx) => (Some(&rest[..idx]), &rest[idx + 1..]),
+                None => return Err(self.err("Tag without interval data")),
+            }
+        } else {
+            (None, line)
+        };
+
+        let fields: Vec<&str> = rest.split(',').collect();
+        let (start, interval, max, encoded) = match fields[..] {
+            [start, interval, max, encoded] => (start, interval, Some(max), encoded),
+            [start, interval, encoded] => (start, interval, None, encoded),
+            _ => return Err(self.err(format!("Expected 3 or 4 fields, found {}", fields.len()))),
+        };
+
+        let start: f64 = start
+            .parse()
+            .map_err(|_| self.err(format!("Invalid start timestamp {:?}", start)))?;
+        let interval_length: f64 = interval
+            .parse()
+            .map_err(|_| self.err(format!("Invalid interval length {:?}", interval)))?;
+        let interval_max = match max {
+            Some(max) => Some(
+                max.parse()
+                    .map_err(|_| self.err(format!("Invalid interval max {:?}", max)))?,
+            ),
+            None => None,
+        };
+
+        if self.base_time.is_none() {
+            // No explicit base time, so infer it as HdrHistogram does: timestamps more than a year
+            // before the start time must be relative to it, otherwise they're absolute.
+            self.base_time = Some(match self.start_time {
+                Some(start_time) if start < start_time - 365.0 * 24.0 * 3600.0 => start_time,
+                _ => 0.0,
+            });
+        }
+        let start_timestamp = start + self.base_time.unwrap_or(0.0);
+
+        if let Some((range_start, range_end)) = self.time_range {
+            if start_timestamp >= range_end {
+                self.done = true;
+                return Ok(None);
+            }
+            if start_timestamp < range_start {
+                return Ok(None);
+            }
+        }
+        if let Some(want) = &self.tag {
+            if want.as_deref() != tag {
+                return Ok(None);
+            }
+        }
+
+        let tag = tag.map(str::to_string);
+        let histogram = Histogram::decode(&encoded.to_string())
+            .map_err(|e| self.err(format!("Invalid histogram: {}", e)))?;
+
+        Ok(Some(LogEntry {
+            start_timestamp,
+            interval_length,
+            interval_max,
+            tag,
+            histogram,
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for LogReader<R> {
+    type Item = Result<LogEntry, HistogramErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line_number += 1;
+                    match self.parse_line() {
+                        Ok(None) => {}
+                        Ok(Some(entry)) => return Some(Ok(entry)),
+                        Err(err) => return Some(Err(err)),
+                    }
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err.into()));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+fn epoch_duration(t: SystemTime) -> Duration {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+/// Format a time since the epoch as an RFC 3339 UTC timestamp with millisecond precision.
+fn format_utc(since_epoch: Duration) -> String {
+    let secs = since_epoch.as_secs();
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // Civil date from days since 1970-01-01, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem / 60) % 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
@@ -0,0 +1,165 @@
+//! Interval recording, wrapping `hdr_interval_recorder`.
+
+use std::mem;
+
+use crate::{ffi, Histogram, HistogramErr};
+
+/// Records values into an active histogram, while allowing a reporting thread to periodically swap
+/// it out and inspect the interval histogram without stopping writers.
+///
+/// Recording is wait-free: writers only enter a critical section of the recorder's
+/// writer/reader phaser, and record with atomic operations, so any number of threads can record
+/// concurrently through `&self`. [`sample`](#method.sample) and
+/// [`sample_and_recycle`](#method.sample_and_recycle) flip the phaser and wait for in-flight
+/// writers to finish before returning the interval histogram.
+///
+/// ```
+/// # use hdrhistogram_c::IntervalRecorder;
+/// let rec = IntervalRecorder::new(1, 1000000, 3).unwrap();
+///
+/// rec.record_value(10);
+/// rec.record_value(20);
+///
+/// let interval = rec.sample();
+/// assert_eq!(interval.total_count(), 2);
+///
+/// rec.record_value(30);
+///
+/// let interval = rec.sample_and_recycle(interval);
+/// assert_eq!(interval.total_count(), 1);
+/// ```
+pub struct IntervalRecorder {
+    rec: *mut ffi::hdr_interval_recorder,
+    lowest_discernible_value: i64,
+    highest_trackable_value: i64,
+    significant_figures: i32,
+}
+
+unsafe impl Send for IntervalRecorder {}
+unsafe impl Sync for IntervalRecorder {}
+
+impl IntervalRecorder {
+    /// Create a new `IntervalRecorder`. The parameters are used for the active histogram, and
+    /// have the same meaning as for `Histogram::new`.
+    pub fn new(
+        lowest_discernible_value: i64,
+        highest_trackable_value: i64,
+        significant_figures: i32,
+    ) -> Result<Self, HistogramErr> {
+        HistogramErr::check_params(
+            lowest_discernible_value,
+            highest_trackable_value,
+            significant_figures,
+        )?;
+
+        let rec = unsafe { ffi::hdr_rust_interval_recorder_alloc() };
+        if rec.is_null() {
+            return Err(HistogramErr::AllocFail);
+        }
+
+        let res = unsafe {
+            ffi::hdr_rust_interval_recorder_init_all(
+                rec,
+                lowest_discernible_value,
+                highest_trackable_value,
+                significant_figures,
+            )
+        };
+
+        if res != 0 {
+            unsafe { ffi::hdr_rust_interval_recorder_free(rec) };
+
+            return Err(HistogramErr::init(res));
+        }
+
+        Ok(IntervalRecorder {
+            rec,
+            lowest_discernible_value,
+            highest_trackable_value,
+            significant_figures,
+        })
+    }
+
+    /// Record a value into the active histogram. Returns false if the value is out of range.
+    #[inline]
+    pub fn record_value(&self, value: i64) -> bool {
+        unsafe { ffi::hdr_interval_recorder_record_value_atomic(self.rec, value) }
+    }
+
+    /// Record multiple counts of a value into the active histogram.
+    #[inline]
+    pub fn record_values(&self, value: i64, count: i64) -> bool {
+        unsafe { ffi::hdr_interval_recorder_record_values_atomic(self.rec, value, count) }
+    }
+
+    /// Record a value into the active histogram, correcting for coordinated omission.
+    #[inline]
+    pub fn record_corrected_value(&self, value: i64, expected_interval: i64) -> bool {
+        unsafe {
+            ffi::hdr_interval_recorder_record_corrected_value_atomic(
+                self.rec,
+                value,
+                expected_interval,
+            )
+        }
+    }
+
+    /// Record multiple counts of a value into the active histogram, correcting for coordinated
+    /// omission.
+    #[inline]
+    pub fn record_corrected_values(&self, value: i64, count: i64, expected_interval: i64) -> bool {
+        unsafe {
+            ffi::hdr_interval_recorder_record_corrected_values_atomic(
+                self.rec,
+                value,
+                count,
+                expected_interval,
+            )
+        }
+    }
+
+    /// Swap a newly allocated, empty histogram in as the active one, and return the histogram
+    /// containing everything recorded since the last sample.
+    ///
+    /// # Panics
+    ///
+    /// Panics if allocating the new histogram fails.
+    pub fn sample(&self) -> Histogram {
+        let fresh = Histogram::new(
+            self.lowest_discernible_value,
+            self.highest_trackable_value,
+            self.significant_figures,
+        )
+        .expect("IntervalRecorder histogram allocation failed");
+
+        self.sample_and_recycle(fresh)
+    }
+
+    /// Reset `recycle` and swap it in as the active histogram, returning the histogram containing
+    /// everything recorded since the last sample.
+    ///
+    /// `recycle` would normally be the histogram returned by the previous sample, once the caller
+    /// has finished with it. This avoids allocating a new histogram for each interval. It should
+    /// have the same configuration as the recorder, otherwise values may be dropped.
+    pub fn sample_and_recycle(&self, recycle: Histogram) -> Histogram {
+        let recycle_ptr = recycle.0;
+        mem::forget(recycle);
+
+        let prev = unsafe { ffi::hdr_interval_recorder_sample_and_recycle(self.rec, recycle_ptr) };
+        assert!(
+            !prev.is_null(),
+            "hdr_interval_recorder_sample_and_recycle failed"
+        );
+
+        Histogram(prev, false)
+    }
+}
+
+impl Drop for IntervalRecorder {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::hdr_interval_recorder_destroy(self.rec);
+            ffi::hdr_rust_interval_recorder_free(self.rec);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Iterators over the contents of a `Histogram`.
+//!
+//! These wrap the `hdr_iter_*` family of iterators from HdrHistogram_c. Each iterator borrows the
+//! `Histogram` it was created from, so the histogram can't be modified while it's being iterated.
+
+use std::marker::PhantomData;
+
+use crate::{ffi, Histogram};
+
+/// Iterator result producing counts.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub struct CountIterItem {
+    /// The count of recorded values in the histogram that were added to the `total_count_to_this_value`
+    /// (below) as a result on this iteration step. Since multiple iteration steps may occur with
+    /// overlapping equivalent value ranges, the count may be lower than the count found at the
+    /// value (e.g. multiple linear steps or percentile levels can occur within a single equivalent
+    /// value range)
+    pub count_added_in_this_iteration_step: i64,
+
+    /// The count of recorded values at `value`.
+    pub count: i64,
+
+    /// The sum of all recorded values in the histogram at values equal or smaller than `value`.
+    pub cumulative_count: i64,
+
+    /// The actual value level that was iterated to by the iterator
+    pub value: i64,
+
+    /// Highest value equivalent to `value`.
+    pub highest_equivalent_value: i64,
+
+    /// Median value equivalent to `value`.
+    pub median_equivalent_value: i64,
+
+    /// Lowest value equivalent to `value`.
+    pub lowest_equivalent_value: i64,
+
+    /// The value iterated from in this step.
+    pub value_iterated_from: i64,
+
+    /// The value iterated to in this step.
+    pub value_iterated_to: i64,
+}
+
+/// Iterator result producing percentiles.
+#[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
+pub struct PercentileIterItem {
+    /// The percentile of recorded values in the histogram at values equal or smaller than `value`.
+    pub percentile: f64,
+
+    /// The count of recorded values at `value`.
+    pub count: i64,
+
+    /// The sum of all recorded values in the histogram at values equal or smaller than `value`.
+    pub cumulative_count: i64,
+
+    /// The actual value level that was iterated to by the iterator
+    pub value: i64,
+
+    /// Highest value equivalent to `value`.
+    pub highest_equivalent_value: i64,
+
+    /// Median value equivalent to `value`.
+    pub median_equivalent_value: i64,
+
+    /// Lowest value equivalent to `value`.
+    pub lowest_equivalent_value: i64,
+
+    /// The value iterated from in this step.
+    pub value_iterated_from: i64,
+
+    /// The value iterated to in this step.
+    pub value_iterated_to: i64,
+}
+
+/// Owned `hdr_iter` state, borrowing the `Histogram` it iterates over.
+struct HdrIter<'a> {
+    iter: *mut ffi::hdr_iter,
+    histo: PhantomData<&'a Histogram>,
+}
+
+impl<'a> HdrIter<'a> {
+    fn new(init: impl FnOnce(*mut ffi::hdr_iter)) -> Self {
+        let iter = unsafe { ffi::hdr_rust_iter_alloc() };
+        assert!(!iter.is_null(), "Iterator allocation failed");
+
+        init(iter);
+
+        HdrIter {
+            iter,
+            histo: PhantomData,
+        }
+    }
+
+    fn next(&mut self) -> bool {
+        unsafe { ffi::hdr_iter_next(self.iter) }
+    }
+
+    fn count_item(&self, count_added_in_this_iteration_step: i64) -> CountIterItem {
+        unsafe {
+            CountIterItem {
+                count_added_in_this_iteration_step,
+                count: ffi::hdr_rust_iter_count(self.iter),
+                cumulative_count: ffi::hdr_rust_iter_cumulative_count(self.iter),
+                value: ffi::hdr_rust_iter_value(self.iter),
+                highest_equivalent_value: ffi::hdr_rust_iter_highest_equivalent_value(self.iter),
+                median_equivalent_value: ffi::hdr_rust_iter_median_equivalent_value(self.iter),
+                lowest_equivalent_value: ffi::hdr_rust_iter_lowest_equivalent_value(self.iter),
+                value_iterated_from: ffi::hdr_rust_iter_value_iterated_from(self.iter),
+                value_iterated_to: ffi::hdr_rust_iter_value_iterated_to(self.iter),
+            }
+        }
+    }
+
+    fn percentile_item(&self) -> PercentileIterItem {
+        unsafe {
+            PercentileIterItem {
+                percentile: ffi::hdr_rust_iter_percentile(self.iter),
+                count: ffi::hdr_rust_iter_count(self.iter),
+                cumulative_count: ffi::hdr_rust_iter_cumulative_count(self.iter),
+                value: ffi::hdr_rust_iter_value(self.iter),
+                highest_equivalent_value: ffi::hdr_rust_iter_highest_equivalent_value(self.iter),
+                median_equivalent_value: ffi::hdr_rust_iter_median_equivalent_value(self.iter),
+                lowest_equivalent_value: ffi::hdr_rust_iter_lowest_equivalent_value(self.iter),
+                value_iterated_from: ffi::hdr_rust_iter_value_iterated_from(self.iter),
+                value_iterated_to: ffi::hdr_rust_iter_value_iterated_to(self.iter),
+            }
+        }
+    }
+}
+
+impl<'a> Drop for HdrIter<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::hdr_rust_iter_free(self.iter) }
+    }
+}
+
+/// Iterator over `Histogram` producing linear buckets.
+pub struct LinearIter<'a>(HdrIter<'a>);
+
+impl<'a> LinearIter<'a> {
+    pub(crate) fn new(histo: &'a Histogram, value_units_per_bucket: i64) -> Self {
+        assert!(
+            value_units_per_bucket > 0,
+            "value_units_per_bucket must be positive"
+        );
+
+        LinearIter(HdrIter::new(|iter| unsafe {
+            ffi::hdr_iter_linear_init(iter, histo.0, value_units_per_bucket)
+        }))
+    }
+}
+
+impl<'a> Iterator for LinearIter<'a> {
+    type Item = CountIterItem;
+
+    fn next(&mut self) -> Option<CountIterItem> {
+        if self.0.next() {
+            let added = unsafe { ffi::hdr_rust_iter_linear_count_added(self.0.iter) };
+            Some(self.0.count_item(added))
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over `Histogram` producing logarithmic buckets.
+pub struct LogIter<'a>(HdrIter<'a>);
+
+impl<'a> LogIter<'a> {
+    pub(crate) fn new(histo: &'a Histogram, value_units_first_bucket: i64, log_base: f64) -> Self {
+        assert!(
+            value_units_first_bucket > 0,
+            "value_units_first_bucket must be positive"
+        );
+        // The C iterator steps with an integer multiply by `log_base as i64`, so bases below 2
+        // truncate to 1 and never advance.
+        assert!(log_base >= 2.0, "log_base must be at least 2");
+
+        LogIter(HdrIter::new(|iter| unsafe {
+            ffi::hdr_iter_log_init(iter, histo.0, value_units_first_bucket, log_base)
+        }))
+    }
+}
+
+impl<'a> Iterator for LogIter<'a> {
+    type Item = CountIterItem;
+
+    fn next(&mut self) -> Option<CountIterItem> {
+        if self.0.next() {
+            let added = unsafe { ffi::hdr_rust_iter_log_count_added(self.0.iter) };
+            Some(self.0.count_item(added))
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over `Histogram` producing recorded values.
+pub struct RecordedIter<'a>(HdrIter<'a>);
+
+impl<'a> RecordedIter<'a> {
+    pub(crate) fn new(histo: &'a Histogram) -> Self {
+        RecordedIter(HdrIter::new(|iter| unsafe {
+            ffi::hdr_iter_recorded_init(iter, histo.0)
+        }))
+    }
+}
+
+impl<'a> Iterator for RecordedIter<'a> {
+    type Item = CountIterItem;
+
+    fn next(&mut self) -> Option<CountIterItem> {
+        if self.0.next() {
+            let added = unsafe { ffi::hdr_rust_iter_recorded_count_added(self.0.iter) };
+            Some(self.0.count_item(added))
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over `Histogram` producing percentile buckets.
+pub struct PercentileIter<'a>(HdrIter<'a>);
+
+impl<'a> PercentileIter<'a> {
+    pub(crate) fn new(histo: &'a Histogram, ticks_per_half_distance: i32) -> Self {
+        PercentileIter(HdrIter::new(|iter| unsafe {
+            ffi::hdr_iter_percentile_init(iter, histo.0, ticks_per_half_distance)
+        }))
+    }
+}
+
+impl<'a> Iterator for PercentileIter<'a> {
+    type Item = PercentileIterItem;
+
+    fn next(&mut self) -> Option<PercentileIterItem> {
+        if self.0.next() {
+            Some(self.0.percentile_item())
+        } else {
+            None
+        }
+    }
+}
@@ -24,24 +24,82 @@
 //! assert_eq!(h.max(), 100);
 //! ```
 
-#![feature(maybe_uninit_slice, vec_split_at_spare)]
-
 use libc::{c_char, c_void};
 use paste::paste;
-use std::{ffi::CStr, mem::MaybeUninit, ptr, str};
-use thiserror::Error;
+use std::{
+    io::{self, Read, Write},
+    ptr, slice,
+};
 
 // mod ffi;
-
+mod atomic;
+mod compare;
+mod config;
+mod duration;
+#[cfg(any(not(feature = "c_backend"), feature = "rust_deflate"))]
+mod encoding;
+mod error;
+mod histogram_log;
+mod interval;
+mod iter;
+mod merge;
+#[cfg(not(feature = "c_backend"))]
+mod native;
+mod ops;
+mod percentiles;
+mod phaser;
+mod rank;
+mod rescale;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub mod slo;
+
+pub use atomic::AtomicHistogram;
+pub use compare::BucketDiff;
+pub use config::{BucketConfig, HistogramConfig};
+pub use duration::{DurationHistogram, TimeUnit};
+pub use error::HistogramErr;
+pub use histogram_log::{LogEntry, LogReader, LogWriter};
+pub use interval::IntervalRecorder;
+pub use iter::{
+    CountIterItem, LinearIter, LogIter, PercentileIter, PercentileIterItem, RecordedIter,
+};
+pub use merge::MergeReport;
+pub use percentiles::PercentilesFormat;
+pub use phaser::{PhaserReaderGuard, PhaserWriterGuard, WriterReaderPhaser};
+pub use rank::{CdfIter, CdfPoint};
+
+// Without the C backend, the native module provides the same functions as the bridge.
+#[cfg(not(feature = "c_backend"))]
+use native as ffi;
+
+// The pure Rust backend compresses with miniz_oxide, which only `rust_deflate` pulls in.
+#[cfg(all(
+    not(feature = "c_backend"),
+    feature = "hdr_log",
+    not(feature = "rust_deflate")
+))]
+compile_error!("the pure Rust backend needs the `rust_deflate` feature for `hdr_log`");
+
+// The C library does the encoding only when it's built with zlib.
+#[cfg(any(not(feature = "c_backend"), feature = "rust_deflate"))]
+use encoding as codec;
+#[cfg(all(feature = "c_backend", not(feature = "rust_deflate")))]
+use ffi as codec;
+
+#[cfg(feature = "c_backend")]
 #[allow(dead_code)]
 #[cxx::bridge]
 mod ffi {
     extern "C++" {
-        include!("HdrHistogram_c/src/hdr_histogram.h");
-        include!("HdrHistogram_c/src/hdr_histogram_log.h");
+        // glue.h includes the HdrHistogram_c headers from the include path set up by build.rs,
+        // so they come from the bundled sources or the system library as appropriate.
         include!("src/glue.h");
 
         type hdr_histogram;
+        type hdr_iter;
+        type hdr_interval_recorder;
+        type hdr_writer_reader_phaser;
 
         unsafe fn hdr_init(
             lowest_discernible_value: i64,
@@ -104,6 +162,75 @@ mod ffi {
         unsafe fn hdr_value_at_index(hdr: *const hdr_histogram, index: i32) -> i64;
         unsafe fn hdr_values_are_equivalent(hdr: *const  hdr_histogram, a: i64, b: i64) -> bool;
         unsafe fn hdr_lowest_equivalent_value(hdr: *const  hdr_histogram, value: i64) -> i64;
+        unsafe fn hdr_next_non_equivalent_value(hdr: *const hdr_histogram, value: i64) -> i64;
+        unsafe fn hdr_median_equivalent_value(hdr: *const hdr_histogram, value: i64) -> i64;
+
+        unsafe fn hdr_iter_recorded_init(iter: *mut hdr_iter, hdr: *const hdr_histogram);
+        unsafe fn hdr_iter_linear_init(
+            iter: *mut hdr_iter,
+            hdr: *const hdr_histogram,
+            value_units_per_bucket: i64,
+        );
+        unsafe fn hdr_iter_log_init(
+            iter: *mut hdr_iter,
+            hdr: *const hdr_histogram,
+            value_units_first_bucket: i64,
+            log_base: f64,
+        );
+        unsafe fn hdr_iter_percentile_init(
+            iter: *mut hdr_iter,
+            hdr: *const hdr_histogram,
+            ticks_per_half_distance: i32,
+        );
+        unsafe fn hdr_iter_next(iter: *mut hdr_iter) -> bool;
+
+        unsafe fn hdr_interval_recorder_destroy(r: *mut hdr_interval_recorder);
+        unsafe fn hdr_interval_recorder_record_value_atomic(
+            r: *mut hdr_interval_recorder,
+            value: i64,
+        ) -> bool;
+        unsafe fn hdr_interval_recorder_record_values_atomic(
+            r: *mut hdr_interval_recorder,
+            value: i64,
+            count: i64,
+        ) -> bool;
+        unsafe fn hdr_interval_recorder_record_corrected_value_atomic(
+            r: *mut hdr_interval_recorder,
+            value: i64,
+            expected_interval: i64,
+        ) -> bool;
+        unsafe fn hdr_interval_recorder_record_corrected_values_atomic(
+            r: *mut hdr_interval_recorder,
+            value: i64,
+            count: i64,
+            expected_interval: i64,
+        ) -> bool;
+        unsafe fn hdr_interval_recorder_sample_and_recycle(
+            r: *mut hdr_interval_recorder,
+            histogram_to_recycle: *mut hdr_histogram,
+        ) -> *mut hdr_histogram;
+
+        unsafe fn hdr_writer_reader_phaser_init(p: *mut hdr_writer_reader_phaser) -> i32;
+        unsafe fn hdr_writer_reader_phaser_destroy(p: *mut hdr_writer_reader_phaser);
+        unsafe fn hdr_phaser_writer_enter(p: *mut hdr_writer_reader_phaser) -> i64;
+        unsafe fn hdr_phaser_writer_exit(
+            p: *mut hdr_writer_reader_phaser,
+            critical_value_at_enter: i64,
+        );
+        unsafe fn hdr_phaser_reader_lock(p: *mut hdr_writer_reader_phaser);
+        unsafe fn hdr_phaser_reader_unlock(p: *mut hdr_writer_reader_phaser);
+        unsafe fn hdr_phaser_flip_phase(p: *mut hdr_writer_reader_phaser, sleep_time_ns: i64);
+
+        unsafe fn hdr_encode_compressed(
+            hdr: *mut hdr_histogram,
+            compressed_histogram: *mut *mut u8,
+            compressed_len: *mut usize,
+        ) -> i32;
+        unsafe fn hdr_decode_compressed(
+            buffer: *mut u8,
+            length: usize,
+            hdr: *mut *mut hdr_histogram,
+        ) -> i32;
 
         unsafe fn hdr_log_encode(hdr: *mut hdr_histogram, encoded: *mut *mut c_char) -> i32;
         unsafe fn hdr_log_decode(
@@ -117,21 +244,67 @@ mod ffi {
         // Rust accessor glue
         unsafe fn hdr_rust_total_count(hdr: *const hdr_histogram) -> i64;
         unsafe fn hdr_rust_counts_len(hdr: *const hdr_histogram) -> i64;
+        unsafe fn hdr_rust_lowest_discernible_value(hdr: *const hdr_histogram) -> i64;
+        unsafe fn hdr_rust_highest_trackable_value(hdr: *const hdr_histogram) -> i64;
+        unsafe fn hdr_rust_significant_figures(hdr: *const hdr_histogram) -> i32;
+        unsafe fn hdr_rust_unit_magnitude(hdr: *const hdr_histogram) -> i32;
+        unsafe fn hdr_rust_sub_bucket_count(hdr: *const hdr_histogram) -> i32;
+        unsafe fn hdr_rust_bucket_count(hdr: *const hdr_histogram) -> i32;
+        unsafe fn hdr_rust_normalizing_index_offset(hdr: *const hdr_histogram) -> i32;
+        unsafe fn hdr_rust_conversion_ratio(hdr: *const hdr_histogram) -> f64;
+        unsafe fn hdr_rust_set_normalizing_index_offset(hdr: *mut hdr_histogram, offset: i32);
+        unsafe fn hdr_rust_set_conversion_ratio(hdr: *mut hdr_histogram, ratio: f64);
+        unsafe fn hdr_rust_get_count_at_index(hdr: *const hdr_histogram, index: i32) -> i64;
+        unsafe fn hdr_rust_set_count_at_index(hdr: *mut hdr_histogram, index: i32, count: i64);
+        unsafe fn hdr_rust_calculate_bucket_config(
+            lowest_discernible_value: i64,
+            highest_trackable_value: i64,
+            significant_figures: i32,
+            unit_magnitude: *mut i32,
+            sub_bucket_count: *mut i32,
+            bucket_count: *mut i32,
+            counts_len: *mut i32,
+        ) -> i32;
+        unsafe fn hdr_rust_memory_size(counts_len: i32) -> usize;
+        unsafe fn hdr_rust_resize(
+            hdr: *const hdr_histogram,
+            highest_trackable_value: i64,
+            result: *mut *mut hdr_histogram,
+        ) -> i32;
         unsafe fn hdr_rust_clone(hdr: *const hdr_histogram) -> *mut hdr_histogram;
+        unsafe fn hdr_rust_reset_internal_counters(hdr: *mut hdr_histogram);
+
+        unsafe fn hdr_rust_iter_alloc() -> *mut hdr_iter;
+        unsafe fn hdr_rust_iter_free(iter: *mut hdr_iter);
+        unsafe fn hdr_rust_iter_count(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_cumulative_count(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_value(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_highest_equivalent_value(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_lowest_equivalent_value(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_median_equivalent_value(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_value_iterated_from(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_value_iterated_to(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_recorded_count_added(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_linear_count_added(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_log_count_added(iter: *const hdr_iter) -> i64;
+        unsafe fn hdr_rust_iter_percentile(iter: *const hdr_iter) -> f64;
+
+        unsafe fn hdr_rust_interval_recorder_alloc() -> *mut hdr_interval_recorder;
+        unsafe fn hdr_rust_interval_recorder_init_all(
+            r: *mut hdr_interval_recorder,
+            lowest_discernible_value: i64,
+            highest_trackable_value: i64,
+            significant_figures: i32,
+        ) -> i32;
+        unsafe fn hdr_rust_interval_recorder_free(r: *mut hdr_interval_recorder);
+
+        unsafe fn hdr_rust_phaser_alloc() -> *mut hdr_writer_reader_phaser;
+        unsafe fn hdr_rust_phaser_free(p: *mut hdr_writer_reader_phaser);
     }
 }
 
-pub struct Histogram(*mut ffi::hdr_histogram);
-
-#[derive(Error, Debug)]
-pub enum HistogramErr {
-    #[error("Allocation failed")]
-    AllocFail,
-    #[error("Initialization failed")]
-    InitFailed,
-    #[error("Encoding/Decoding failed: {}", _0)]
-    CodecFailed(&'static str),
-}
+/// The `bool` is whether the histogram automatically resizes to fit out of range values.
+pub struct Histogram(*mut ffi::hdr_histogram, bool);
 
 unsafe impl Send for Histogram {}
 
@@ -164,6 +337,12 @@ impl Histogram {
         highest_trackable_value: i64,
         significant_figures: i32,
     ) -> Result<Self, HistogramErr> {
+        HistogramErr::check_params(
+            lowest_discernible_value,
+            highest_trackable_value,
+            significant_figures,
+        )?;
+
         let mut ret: *mut ffi::hdr_histogram = ptr::null_mut();
         unsafe {
             let res = ffi::hdr_init(
@@ -173,25 +352,162 @@ impl Histogram {
                 &mut ret,
             );
 
-            if res == libc::ENOMEM || ret.is_null() {
+            if res != 0 {
+                return Err(HistogramErr::init(res));
+            }
+
+            if ret.is_null() {
                 return Err(HistogramErr::AllocFail);
             }
+        }
 
-            if res != 0 {
-                return Err(HistogramErr::InitFailed);
-            };
+        Ok(Histogram(ret, false))
+    }
+
+    /// Enable or disable auto-resizing. When enabled, recording a value above the highest
+    /// trackable value, or adding a histogram containing one, grows the histogram to fit instead
+    /// of dropping the value. Existing counts and the precision are preserved.
+    ///
+    /// `AtomicHistogram` never resizes, as that requires exclusive access.
+    ///
+    /// ```
+    /// # use hdrhistogram_c::Histogram;
+    /// let mut h = Histogram::new(1, 1000, 3).unwrap();
+    /// h.set_auto_resize(true);
+    ///
+    /// assert!(h.record_value(1000000));
+    /// assert!(h.highest_trackable_value() >= 1000000);
+    /// assert_eq!(h.count_at_value(1000000), 1);
+    /// ```
+    pub fn set_auto_resize(&mut self, enabled: bool) {
+        self.1 = enabled;
+    }
+
+    /// Whether auto-resizing is enabled.
+    pub fn is_auto_resize(&self) -> bool {
+        self.1
+    }
+
+    /// Grow the histogram so it can track `value`. At least doubles the highest trackable value,
+    /// so repeated resizes are amortized.
+    fn resize(&mut self, value: i64) -> Result<(), HistogramErr> {
+        let highest_trackable_value = value.max(self.highest_trackable_value().saturating_mul(2));
+
+        let mut new: *mut ffi::hdr_histogram = ptr::null_mut();
+        let res = unsafe { ffi::hdr_rust_resize(self.0, highest_trackable_value, &mut new) };
+        if res != 0 {
+            return Err(HistogramErr::init(res));
         }
 
-        Ok(Histogram(ret))
+        unsafe { ffi::hdr_close(self.0) };
+        self.0 = new;
+
+        Ok(())
+    }
+
+    /// Run `record`, retrying after a resize if it fails because `value` is too large.
+    #[inline]
+    fn record_resizing(
+        &mut self,
+        value: i64,
+        record: impl Fn(*mut ffi::hdr_histogram) -> bool,
+    ) -> bool {
+        record(self.0)
+            || (self.1
+                && value > self.highest_trackable_value()
+                && self.resize(value).is_ok()
+                && record(self.0))
+    }
+
+    /// Resize before adding `other`, if needed to fit its values.
+    fn resize_for(&mut self, other: &Histogram) {
+        let max = other.max();
+        if self.1 && max > self.highest_trackable_value() {
+            // If this fails, the out of range values are reported as dropped by the add.
+            let _ = self.resize(max);
+        }
     }
 
     ffi!(mut reset);
     ffi!(get_memory_size -> usize);
 
-    ffi!(mut record_value(value: i64) -> bool);
-    ffi!(mut record_values(value: i64, count: i64) -> bool);
-    ffi!(mut record_corrected_value(value: i64, expected_interval: i64) -> bool);
-    ffi!(mut record_corrected_values(value: i64, count: i64, expected_interval: i64) -> bool);
+    #[inline]
+    pub fn record_value(&mut self, value: i64) -> bool {
+        self.record_resizing(value, |h| unsafe { ffi::hdr_record_value(h, value) })
+    }
+
+    #[inline]
+    pub fn record_values(&mut self, value: i64, count: i64) -> bool {
+        self.record_resizing(value, |h| unsafe {
+            ffi::hdr_record_values(h, value, count)
+        })
+    }
+
+    #[inline]
+    pub fn record_corrected_value(&mut self, value: i64, expected_interval: i64) -> bool {
+        self.record_resizing(value, |h| unsafe {
+            ffi::hdr_record_corrected_value(h, value, expected_interval)
+        })
+    }
+
+    #[inline]
+    pub fn record_corrected_values(
+        &mut self,
+        value: i64,
+        count: i64,
+        expected_interval: i64,
+    ) -> bool {
+        self.record_resizing(value, |h| unsafe {
+            ffi::hdr_record_corrected_values(h, value, count, expected_interval)
+        })
+    }
+
+    /// Record a value, returning an error if it's out of range.
+    pub fn try_record_value(&mut self, value: i64) -> Result<(), HistogramErr> {
+        self.try_record(value, |h| h.record_value(value))
+    }
+
+    /// Record multiple counts of a value, returning an error if it's out of range.
+    pub fn try_record_values(&mut self, value: i64, count: i64) -> Result<(), HistogramErr> {
+        self.try_record(value, |h| h.record_values(value, count))
+    }
+
+    /// Record a value correcting for coordinated omission, returning an error if it's out of
+    /// range.
+    pub fn try_record_corrected_value(
+        &mut self,
+        value: i64,
+        expected_interval: i64,
+    ) -> Result<(), HistogramErr> {
+        self.try_record(value, |h| {
+            h.record_corrected_value(value, expected_interval)
+        })
+    }
+
+    /// Record multiple counts of a value correcting for coordinated omission, returning an error
+    /// if it's out of range.
+    pub fn try_record_corrected_values(
+        &mut self,
+        value: i64,
+        count: i64,
+        expected_interval: i64,
+    ) -> Result<(), HistogramErr> {
+        self.try_record(value, |h| {
+            h.record_corrected_values(value, count, expected_interval)
+        })
+    }
+
+    fn try_record(
+        &mut self,
+        value: i64,
+        record: impl FnOnce(&mut Self) -> bool,
+    ) -> Result<(), HistogramErr> {
+        if record(self) {
+            Ok(())
+        } else {
+            Err(HistogramErr::ValueOutOfRange(value))
+        }
+    }
 
     ffi!(min -> i64);
     ffi!(max -> i64);
@@ -206,35 +522,68 @@ impl Histogram {
     ffi!(lowest_equivalent_value(value: i64) -> i64);
 
     pub fn value_at_percentiles(&self, percentiles: &[f64]) -> Box<[i64]> {
-        let mut values: Vec<i64> = Vec::with_capacity(percentiles.len());
-        let (_, uninit) = values.split_at_spare_mut();
+        let mut values = vec![0; percentiles.len()].into_boxed_slice();
+        self.value_at_percentiles_into(percentiles, &mut values);
+        values
+    }
+
+    /// Like `value_at_percentiles`, but writes the values into a caller-provided buffer, so it
+    /// can be reused without allocating. `percentiles` must be in ascending order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` isn't the same length as `percentiles`.
+    ///
+    /// ```
+    /// # use hdrhistogram_c::Histogram;
+    /// let mut h = Histogram::new(1, 100000, 3).unwrap();
+    /// for v in 1..=1000 {
+    ///     h.record_value(v);
+    /// }
+    ///
+    /// let mut values = [0; 3];
+    /// h.value_at_percentiles_into(&[50.0, 90.0, 100.0], &mut values);
+    /// assert_eq!(values, [500, 900, 1000]);
+    /// ```
+    pub fn value_at_percentiles_into(&self, percentiles: &[f64], values: &mut [i64]) {
+        assert_eq!(
+            percentiles.len(),
+            values.len(),
+            "percentiles and values lengths differ"
+        );
 
         let res = unsafe {
             ffi::hdr_value_at_percentiles(
                 self.0,
                 percentiles.as_ptr(),
-                MaybeUninit::slice_as_mut_ptr(uninit),
+                values.as_mut_ptr(),
                 percentiles.len(),
             )
         };
 
         assert_eq!(res, 0, "hdr_value_at_percentiles invalid pointer?");
-
-        // SAFETY: values have now been initialized
-        unsafe { values.set_len(percentiles.len()) };
-
-        values.into_boxed_slice()
     }
 
     pub fn add(&mut self, other: &Histogram) -> i64 {
+        self.resize_for(other);
         unsafe { ffi::hdr_add(self.0, other.0) }
     }
 
+    /// Add the counts of `other`, returning an error if any were out of range and dropped. The
+    /// counts in range are added regardless.
+    pub fn try_add(&mut self, other: &Histogram) -> Result<(), HistogramErr> {
+        match self.add(other) {
+            0 => Ok(()),
+            dropped => Err(HistogramErr::CountsDropped(dropped)),
+        }
+    }
+
     pub fn add_while_correcting_for_coordinated_omission(
         &mut self,
         other: &Histogram,
         expected_interval: i64,
     ) -> i64 {
+        self.resize_for(other);
         unsafe {
             ffi::hdr_add_while_correcting_for_coordinated_omission(
                 self.0,
@@ -244,6 +593,69 @@ impl Histogram {
         }
     }
 
+    /// Subtract the counts of `other` from this histogram, bucket by bucket.
+    ///
+    /// This is the inverse of `add`, for recovering the values recorded in an interval from two
+    /// cumulative snapshots. Both histograms must have the same unit magnitude and sub-bucket
+    /// count, so their buckets line up, and `other` can't have counts above this histogram's
+    /// range. On error nothing is subtracted.
+    ///
+    /// ```
+    /// # use hdrhistogram_c::Histogram;
+    /// let mut h = Histogram::new(1, 100000, 3).unwrap();
+    /// h.record_values(10, 3);
+    /// let snapshot = h.clone();
+    /// h.record_values(20, 2);
+    ///
+    /// h.subtract(&snapshot).unwrap();
+    /// assert_eq!(h.total_count(), 2);
+    /// assert_eq!(h.min(), 20);
+    /// ```
+    pub fn subtract(&mut self, other: &Histogram) -> Result<(), HistogramErr> {
+        if self.unit_magnitude() != other.unit_magnitude()
+            || self.sub_bucket_count() != other.sub_bucket_count()
+        {
+            return Err(HistogramErr::IncompatibleHistograms);
+        }
+
+        let counts_len = self.get_counts_len() as i32;
+        let other_counts_len = other.get_counts_len() as i32;
+
+        for i in 0..other_counts_len {
+            let count = unsafe { ffi::hdr_count_at_index(other.0, i) };
+            if count == 0 {
+                continue;
+            }
+
+            if i >= counts_len {
+                let value = unsafe { ffi::hdr_value_at_index(other.0, i) };
+                return Err(HistogramErr::ValueOutOfRange(value));
+            }
+            if unsafe { ffi::hdr_count_at_index(self.0, i) } < count {
+                let value = unsafe { ffi::hdr_value_at_index(self.0, i) };
+                return Err(HistogramErr::NegativeCount(value));
+            }
+        }
+
+        unsafe {
+            // Counts are read through each histogram's normalizing index offset, so writes must
+            // apply it too.
+            let offset = ffi::hdr_rust_normalizing_index_offset(self.0);
+
+            for i in 0..other_counts_len.min(counts_len) {
+                let count = ffi::hdr_count_at_index(other.0, i);
+                if count != 0 {
+                    let remaining = ffi::hdr_count_at_index(self.0, i) - count;
+                    let index = (i - offset).rem_euclid(counts_len);
+                    ffi::hdr_rust_set_count_at_index(self.0, index, remaining);
+                }
+            }
+            ffi::hdr_rust_reset_internal_counters(self.0);
+        }
+
+        Ok(())
+    }
+
     pub fn total_count(&self) -> i64 {
         unsafe { ffi::hdr_rust_total_count(self.0) }
     }
@@ -252,21 +664,98 @@ impl Histogram {
         unsafe { ffi::hdr_rust_counts_len(self.0) }
     }
 
+    /// Lowest value which can be discerned from 0.
+    pub fn lowest_discernible_value(&self) -> i64 {
+        unsafe { ffi::hdr_rust_lowest_discernible_value(self.0) }
+    }
+
+    /// Highest value which can be recorded.
+    pub fn highest_trackable_value(&self) -> i64 {
+        unsafe { ffi::hdr_rust_highest_trackable_value(self.0) }
+    }
+
+    /// Number of significant decimal digits of precision.
+    pub fn significant_figures(&self) -> i32 {
+        unsafe { ffi::hdr_rust_significant_figures(self.0) }
+    }
+
+    /// Base 2 logarithm of the largest power of two not above the lowest discernible value.
+    pub fn unit_magnitude(&self) -> i32 {
+        unsafe { ffi::hdr_rust_unit_magnitude(self.0) }
+    }
+
+    /// Number of sub-buckets in each bucket.
+    pub fn sub_bucket_count(&self) -> i32 {
+        unsafe { ffi::hdr_rust_sub_bucket_count(self.0) }
+    }
+
+    /// Number of buckets.
+    pub fn bucket_count(&self) -> i32 {
+        unsafe { ffi::hdr_rust_bucket_count(self.0) }
+    }
+
+    /// The configuration this histogram was created with.
+    pub fn config(&self) -> HistogramConfig {
+        HistogramConfig::new()
+            .lowest_discernible_value(self.lowest_discernible_value())
+            .highest_trackable_value(self.highest_trackable_value())
+            .significant_figures(self.significant_figures())
+            .auto_resize(self.is_auto_resize())
+    }
+
+    /// Linear iterator over values. Results are returned in equally weighted buckets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value_units_per_bucket` isn't positive, as the iterator would never advance.
+    ///
+    /// ```
+    /// # use hdrhistogram_c::Histogram;
+    /// let mut h = Histogram::new(1, 100000, 3).unwrap();
+    /// for i in 1..100 { h.record_values(i, i); }
+    /// for (i, c) in h.linear_iter(1).enumerate() {    // 100 buckets
+    ///     # assert_eq!(i as i64 + 1, c.count_added_in_this_iteration_step);
+    ///     println!("bucket {} = {}", i, c.count_added_in_this_iteration_step);
+    /// }
+    /// ```
+    pub fn linear_iter(&self, value_units_per_bucket: i64) -> LinearIter<'_> {
+        LinearIter::new(self, value_units_per_bucket)
+    }
+
+    /// Logarithmic iterator over values. Results are returned in logarithmically weighted buckets,
+    /// the first covering `value_units_first_bucket` and each subsequent one `log_base` times
+    /// larger than the last.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value_units_first_bucket` isn't positive or `log_base` is less than 2, as the
+    /// iterator would never advance.
+    pub fn log_iter(&self, value_units_first_bucket: i64, log_base: f64) -> LogIter<'_> {
+        LogIter::new(self, value_units_first_bucket, log_base)
+    }
+
+    /// Iterator over recorded values, visiting each distinct bucket with a non-zero count.
+    pub fn recorded_iter(&self) -> RecordedIter<'_> {
+        RecordedIter::new(self)
+    }
+
+    /// Iterator over percentiles. `ticks_per_half_distance` sets the number of steps taken
+    /// for each halving of the distance to 100%.
+    pub fn percentile_iter(&self, ticks_per_half_distance: i32) -> PercentileIter<'_> {
+        PercentileIter::new(self, ticks_per_half_distance)
+    }
+
     /// Encode `Histogram` state into a Base64 encoded string.
     pub fn encode(&self) -> Result<String, HistogramErr> {
         let mut p: *mut c_char = ptr::null_mut();
-        let r = unsafe { ffi::hdr_log_encode(self.0, &mut p) };
+        let r = unsafe { codec::hdr_log_encode(self.0, &mut p) };
 
         if r != 0 || p.is_null() {
-            Err(HistogramErr::CodecFailed(
-                str::from_utf8(unsafe { CStr::from_ptr(ffi::hdr_strerror(r)) }.to_bytes()).unwrap(),
-            ))
+            Err(HistogramErr::codec(r, || 0))
         } else {
             let sz = unsafe { libc::strlen(p) as usize };
             let s = unsafe {
-                let mut v = Vec::with_capacity(sz);
-                v.set_len(sz);
-                ptr::copy(p as *const u8, v.as_mut_ptr(), sz);
+                let v = slice::from_raw_parts(p as *const u8, sz).to_vec();
                 libc::free(p as *mut c_void);
 
                 String::from_utf8_unchecked(v)
@@ -281,16 +770,84 @@ impl Histogram {
         let bytes = base64.as_bytes();
         let mut h: *mut ffi::hdr_histogram = ptr::null_mut();
         let r =
-            unsafe { ffi::hdr_log_decode(&mut h, bytes.as_ptr() as *mut c_char, bytes.len()) };
+            unsafe { codec::hdr_log_decode(&mut h, bytes.as_ptr() as *mut c_char, bytes.len()) };
 
         if r != 0 || h.is_null() {
-            Err(HistogramErr::CodecFailed(
-                str::from_utf8(unsafe { CStr::from_ptr(ffi::hdr_strerror(r)) }.to_bytes()).unwrap(),
-            ))
+            Err(match error::base64_cookie(base64) {
+                Some(cookie) => HistogramErr::codec(r, || cookie),
+                None => HistogramErr::Truncated,
+            })
         } else {
-            Ok(Histogram(h))
+            Ok(Histogram(h, false))
         }
     }
+
+    /// Encode `Histogram` state into the binary V2 compressed format, without Base64 encoding.
+    ///
+    /// This is the same format HdrHistogram's Java `encodeIntoCompressedByteBuffer` produces.
+    pub fn encode_to_vec(&self) -> Result<Vec<u8>, HistogramErr> {
+        let mut p: *mut u8 = ptr::null_mut();
+        let mut len: usize = 0;
+        let r = unsafe { codec::hdr_encode_compressed(self.0, &mut p, &mut len) };
+
+        if r != 0 || p.is_null() {
+            Err(HistogramErr::codec(r, || 0))
+        } else {
+            let v = unsafe {
+                let v = slice::from_raw_parts(p, len).to_vec();
+                libc::free(p as *mut c_void);
+                v
+            };
+
+            Ok(v)
+        }
+    }
+
+    /// Encode `Histogram` state in the binary V2 compressed format into `writer`.
+    pub fn encode_into(&self, writer: &mut impl Write) -> Result<(), HistogramErr> {
+        writer.write_all(&self.encode_to_vec()?)?;
+        Ok(())
+    }
+
+    /// Decode `Histogram` state from binary V2 compressed data, as generated by `encode_to_vec`.
+    pub fn decode_from_slice(bytes: &[u8]) -> Result<Histogram, HistogramErr> {
+        let mut h: *mut ffi::hdr_histogram = ptr::null_mut();
+        let r =
+            unsafe { codec::hdr_decode_compressed(bytes.as_ptr() as *mut u8, bytes.len(), &mut h) };
+
+        if r != 0 || h.is_null() {
+            Err(match error::binary_cookie(bytes) {
+                Some(cookie) => HistogramErr::codec(r, || cookie),
+                None => HistogramErr::Truncated,
+            })
+        } else {
+            Ok(Histogram(h, false))
+        }
+    }
+
+    /// Decode one `Histogram` in binary V2 compressed format from `reader`.
+    ///
+    /// This reads exactly the bytes of the encoded histogram, using the length in its header, so
+    /// several encoded histograms can be read back-to-back from one stream.
+    pub fn decode_from(mut reader: impl Read) -> Result<Histogram, HistogramErr> {
+        // Header is a 4 byte cookie followed by the 4 byte big-endian length of the compressed
+        // payload.
+        let mut bytes = vec![0; 8];
+        reader.read_exact(&mut bytes)?;
+
+        let len = i32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        if len < 0 {
+            return Err(HistogramErr::InvalidHistogram);
+        }
+
+        // Don't trust the length for the allocation: only grow the buffer as data arrives.
+        let read = reader.take(len as u64).read_to_end(&mut bytes)?;
+        if read < len as usize {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Self::decode_from_slice(&bytes)
+    }
 }
 
 impl Clone for Histogram {
@@ -298,7 +855,7 @@ impl Clone for Histogram {
         let new = unsafe { ffi::hdr_rust_clone(self.0) };
         assert!(!new.is_null(), "Clone allocation failed");
 
-        Histogram(new)
+        Histogram(new, self.1)
     }
 }
 
@@ -0,0 +1,87 @@
+//! Merging histograms with different configurations.
+
+use crate::{ffi, Histogram};
+
+/// What happened to the counts merged by
+/// [`Histogram::merge_rebucketed`](struct.Histogram.html#method.merge_rebucketed).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Counts recorded into the destination, including clamped ones.
+    pub merged: i64,
+    /// Counts above the destination's range, recorded at its highest trackable value instead.
+    pub clamped: i64,
+    /// Counts recorded into a destination bucket wider than their source bucket, so their values
+    /// are known less precisely than before.
+    pub precision_lost: i64,
+    /// Counts which couldn't be recorded even at the destination's highest trackable value.
+    pub dropped: i64,
+}
+
+impl Histogram {
+    /// Merge the counts of `other`, which may have a different configuration, by recording each
+    /// of its buckets' counts at the bucket's median equivalent value.
+    ///
+    /// Unlike `add`, nothing is dropped: counts above this histogram's range are recorded at its
+    /// highest trackable value, unless it auto-resizes. The returned report says how many counts
+    /// were clamped, and how many lost precision because this histogram's buckets are coarser.
+    /// Counts are only dropped, and reported as such, if even clamping fails.
+    ///
+    /// To merge without losing anything, merge into a histogram with a
+    /// [`covering`](struct.HistogramConfig.html#method.covering) configuration:
+    ///
+    /// ```
+    /// # use hdrhistogram_c::{Histogram, HistogramConfig, MergeReport};
+    /// let mut a = Histogram::new(1, 1000, 2).unwrap();
+    /// a.record_value(123);
+    /// let mut b = Histogram::new(1000, 100000000, 3).unwrap();
+    /// b.record_value(12345678);
+    ///
+    /// let mut merged = HistogramConfig::covering(vec![&a, &b]).build().unwrap();
+    /// for h in &[&a, &b] {
+    ///     let report = merged.merge_rebucketed(h);
+    ///     assert_eq!(report.clamped + report.precision_lost, 0);
+    /// }
+    /// assert_eq!(merged.total_count(), 2);
+    ///
+    /// let report = a.merge_rebucketed(&b);
+    /// assert_eq!(
+    ///     report,
+    ///     MergeReport { merged: 1, clamped: 1, precision_lost: 0, dropped: 0 }
+    /// );
+    /// ```
+    pub fn merge_rebucketed(&mut self, other: &Histogram) -> MergeReport {
+        let mut report = MergeReport::default();
+
+        for i in 0..other.get_counts_len() as i32 {
+            let count = unsafe { ffi::hdr_count_at_index(other.0, i) };
+            if count == 0 {
+                continue;
+            }
+
+            let value = unsafe { ffi::hdr_value_at_index(other.0, i) };
+            let median = unsafe { ffi::hdr_median_equivalent_value(other.0, value) };
+
+            if self.record_values(median, count) {
+                report.merged += count;
+                if equivalent_range(self, median) > equivalent_range(other, value) {
+                    report.precision_lost += count;
+                }
+            } else if self.record_values(self.highest_trackable_value(), count) {
+                report.merged += count;
+                report.clamped += count;
+            } else {
+                report.dropped += count;
+            }
+        }
+
+        report
+    }
+}
+
+/// Size of the range of values equivalent to `value`.
+fn equivalent_range(h: &Histogram, value: i64) -> i64 {
+    unsafe {
+        ffi::hdr_next_non_equivalent_value(h.0, value)
+            - ffi::hdr_lowest_equivalent_value(h.0, value)
+    }
+}
@@ -0,0 +1,1498 @@
+//! Pure Rust implementation of the HdrHistogram_c functions bridged in `lib.rs`.
+//!
+//! This is used in place of the cxx bridge when the `c_backend` feature is disabled. It's a port
+//! of the C library with the same function names, signatures and semantics, so the rest of the
+//! crate is identical whichever backend is selected. Histograms are still handled through raw
+//! pointers, allocated here and freed by `hdr_close`, and buffers handed back to the caller are
+//! allocated with `malloc` so they can be released with `libc::free` as the C ones are.
+//!
+//! Counts and the running totals are stored as atomics, so the `_atomic` recording functions can
+//! operate through a shared reference. The non-atomic functions use plain loads and stores.
+
+#![allow(dead_code, non_camel_case_types, clippy::missing_safety_doc)]
+
+use std::{
+    mem, ptr, slice,
+    sync::atomic::{AtomicBool, AtomicI64, AtomicPtr, Ordering},
+    thread,
+    time::Duration,
+};
+
+use libc::c_char;
+
+use crate::error::{
+    HDR_COMPRESSION_COOKIE_MISMATCH, HDR_DEFLATE_FAIL, HDR_DEFLATE_INIT_FAIL,
+    HDR_ENCODED_INPUT_TOO_LONG, HDR_ENCODING_COOKIE_MISMATCH, HDR_INFLATE_FAIL,
+    HDR_INFLATE_INIT_FAIL, HDR_LOG_INVALID_VERSION, HDR_TRAILING_ZEROS_INVALID,
+    HDR_VALUE_TRUNCATED,
+};
+
+const RELAXED: Ordering = Ordering::Relaxed;
+const SEQ_CST: Ordering = Ordering::SeqCst;
+
+pub struct hdr_histogram {
+    lowest_discernible_value: i64,
+    highest_trackable_value: i64,
+    unit_magnitude: i32,
+    significant_figures: i32,
+    sub_bucket_half_count_magnitude: i32,
+    sub_bucket_half_count: i32,
+    sub_bucket_mask: i64,
+    sub_bucket_count: i32,
+    bucket_count: i32,
+    min_value: AtomicI64,
+    max_value: AtomicI64,
+    normalizing_index_offset: i32,
+    conversion_ratio: f64,
+    counts_len: i32,
+    total_count: AtomicI64,
+    counts: Box<[AtomicI64]>,
+}
+
+/// Equivalent of `struct hdr_histogram_bucket_config`.
+struct BucketConfig {
+    lowest_discernible_value: i64,
+    highest_trackable_value: i64,
+    unit_magnitude: i32,
+    significant_figures: i32,
+    sub_bucket_half_count_magnitude: i32,
+    sub_bucket_half_count: i32,
+    sub_bucket_mask: i64,
+    sub_bucket_count: i32,
+    bucket_count: i32,
+    counts_len: i32,
+}
+
+fn buckets_needed_to_cover_value(value: i64, sub_bucket_count: i32, unit_magnitude: i32) -> i32 {
+    let mut smallest_untrackable_value = (sub_bucket_count as i64) << unit_magnitude;
+    let mut buckets_needed = 1;
+
+    while smallest_untrackable_value <= value {
+        if smallest_untrackable_value > i64::MAX / 2 {
+            return buckets_needed + 1;
+        }
+        smallest_untrackable_value <<= 1;
+        buckets_needed += 1;
+    }
+
+    buckets_needed
+}
+
+fn calculate_bucket_config(
+    lowest_discernible_value: i64,
+    highest_trackable_value: i64,
+    significant_figures: i32,
+) -> Result<BucketConfig, i32> {
+    if lowest_discernible_value < 1
+        || !(1..=5).contains(&significant_figures)
+        || lowest_discernible_value.saturating_mul(2) > highest_trackable_value
+    {
+        return Err(libc::EINVAL);
+    }
+
+    // These use floating point logs, as the C library does, so the layout is identical.
+    let largest_value_with_single_unit_resolution = 2 * 10i64.pow(significant_figures as u32);
+    let sub_bucket_count_magnitude =
+        ((largest_value_with_single_unit_resolution as f64).ln() / 2f64.ln()).ceil() as i32;
+    let sub_bucket_half_count_magnitude = sub_bucket_count_magnitude.max(1) - 1;
+    let unit_magnitude = ((lowest_discernible_value as f64).ln() / 2f64.ln()) as i32;
+
+    let sub_bucket_count = 1i32 << (sub_bucket_half_count_magnitude + 1);
+    let sub_bucket_half_count = sub_bucket_count / 2;
+    let sub_bucket_mask = (sub_bucket_count as i64 - 1) << unit_magnitude;
+
+    if unit_magnitude + sub_bucket_half_count_magnitude > 61 {
+        return Err(libc::EINVAL);
+    }
+
+    let bucket_count =
+        buckets_needed_to_cover_value(highest_trackable_value, sub_bucket_count, unit_magnitude);
+
+    Ok(BucketConfig {
+        lowest_discernible_value,
+        highest_trackable_value,
+        unit_magnitude,
+        significant_figures,
+        sub_bucket_half_count_magnitude,
+        sub_bucket_half_count,
+        sub_bucket_mask,
+        sub_bucket_count,
+        bucket_count,
+        counts_len: (bucket_count + 1) * sub_bucket_half_count,
+    })
+}
+
+fn sub_bucket_index(value: i64, bucket_index: i32, unit_magnitude: i32) -> i32 {
+    (value >> (bucket_index + unit_magnitude)) as i32
+}
+
+fn value_from_index(bucket_index: i32, sub_bucket_index: i32, unit_magnitude: i32) -> i64 {
+    (sub_bucket_index as i64) << (bucket_index + unit_magnitude)
+}
+
+impl hdr_histogram {
+    fn new(cfg: &BucketConfig) -> Result<Box<Self>, i32> {
+        let mut counts = Vec::new();
+        counts
+            .try_reserve_exact(cfg.counts_len as usize)
+            .map_err(|_| libc::ENOMEM)?;
+        counts.resize_with(cfg.counts_len as usize, || AtomicI64::new(0));
+
+        Ok(Box::new(hdr_histogram {
+            lowest_discernible_value: cfg.lowest_discernible_value,
+            highest_trackable_value: cfg.highest_trackable_value,
+            unit_magnitude: cfg.unit_magnitude,
+            significant_figures: cfg.significant_figures,
+            sub_bucket_half_count_magnitude: cfg.sub_bucket_half_count_magnitude,
+            sub_bucket_half_count: cfg.sub_bucket_half_count,
+            sub_bucket_mask: cfg.sub_bucket_mask,
+            sub_bucket_count: cfg.sub_bucket_count,
+            bucket_count: cfg.bucket_count,
+            min_value: AtomicI64::new(i64::MAX),
+            max_value: AtomicI64::new(0),
+            normalizing_index_offset: 0,
+            conversion_ratio: 1.0,
+            counts_len: cfg.counts_len,
+            total_count: AtomicI64::new(0),
+            counts: counts.into_boxed_slice(),
+        }))
+    }
+
+    fn copy(&self) -> Box<Self> {
+        Box::new(hdr_histogram {
+            min_value: AtomicI64::new(self.min_value.load(RELAXED)),
+            max_value: AtomicI64::new(self.max_value.load(RELAXED)),
+            total_count: AtomicI64::new(self.total_count.load(RELAXED)),
+            counts: self
+                .counts
+                .iter()
+                .map(|c| AtomicI64::new(c.load(RELAXED)))
+                .collect(),
+            ..*self
+        })
+    }
+
+    fn bucket_index(&self, value: i64) -> i32 {
+        let pow2ceiling = 64 - (value | self.sub_bucket_mask).leading_zeros() as i32;
+        pow2ceiling - self.unit_magnitude - (self.sub_bucket_half_count_magnitude + 1)
+    }
+
+    fn counts_index(&self, bucket_index: i32, sub_bucket_index: i32) -> i32 {
+        let bucket_base_index = (bucket_index + 1) << self.sub_bucket_half_count_magnitude;
+        let offset_in_bucket = sub_bucket_index - self.sub_bucket_half_count;
+        bucket_base_index + offset_in_bucket
+    }
+
+    fn counts_index_for(&self, value: i64) -> i32 {
+        let bucket_index = self.bucket_index(value);
+        let sub_bucket_index = sub_bucket_index(value, bucket_index, self.unit_magnitude);
+        self.counts_index(bucket_index, sub_bucket_index)
+    }
+
+    fn normalize_index(&self, index: i32) -> i32 {
+        if self.normalizing_index_offset == 0 {
+            return index;
+        }
+
+        let normalized_index = index - self.normalizing_index_offset;
+        if normalized_index < 0 {
+            normalized_index + self.counts_len
+        } else if normalized_index >= self.counts_len {
+            normalized_index - self.counts_len
+        } else {
+            normalized_index
+        }
+    }
+
+    fn count_at_index(&self, index: i32) -> i64 {
+        self.counts[self.normalize_index(index) as usize].load(RELAXED)
+    }
+
+    fn value_at_index(&self, index: i32) -> i64 {
+        let mut bucket_index = (index >> self.sub_bucket_half_count_magnitude) - 1;
+        let mut sub_bucket_index =
+            (index & (self.sub_bucket_half_count - 1)) + self.sub_bucket_half_count;
+
+        if bucket_index < 0 {
+            sub_bucket_index -= self.sub_bucket_half_count;
+            bucket_index = 0;
+        }
+
+        value_from_index(bucket_index, sub_bucket_index, self.unit_magnitude)
+    }
+
+    fn size_of_equivalent_value_range_given_bucket_indices(
+        &self,
+        bucket_index: i32,
+        sub_bucket_index: i32,
+    ) -> i64 {
+        let adjusted_bucket = if sub_bucket_index >= self.sub_bucket_count {
+            bucket_index + 1
+        } else {
+            bucket_index
+        };
+        1i64 << (self.unit_magnitude + adjusted_bucket)
+    }
+
+    fn size_of_equivalent_value_range(&self, value: i64) -> i64 {
+        let bucket_index = self.bucket_index(value);
+        let sub_bucket_index = sub_bucket_index(value, bucket_index, self.unit_magnitude);
+        self.size_of_equivalent_value_range_given_bucket_indices(bucket_index, sub_bucket_index)
+    }
+
+    fn lowest_equivalent_value(&self, value: i64) -> i64 {
+        let bucket_index = self.bucket_index(value);
+        let sub_bucket_index = sub_bucket_index(value, bucket_index, self.unit_magnitude);
+        value_from_index(bucket_index, sub_bucket_index, self.unit_magnitude)
+    }
+
+    fn next_non_equivalent_value(&self, value: i64) -> i64 {
+        self.lowest_equivalent_value(value) + self.size_of_equivalent_value_range(value)
+    }
+
+    fn highest_equivalent_value(&self, value: i64) -> i64 {
+        self.next_non_equivalent_value(value) - 1
+    }
+
+    fn median_equivalent_value(&self, value: i64) -> i64 {
+        self.lowest_equivalent_value(value) + (self.size_of_equivalent_value_range(value) >> 1)
+    }
+
+    fn record_values(&self, value: i64, count: i64) -> bool {
+        if value < 0 {
+            return false;
+        }
+
+        let counts_index = self.counts_index_for(value);
+        if counts_index < 0 || self.counts_len <= counts_index {
+            return false;
+        }
+
+        let c = &self.counts[self.normalize_index(counts_index) as usize];
+        c.store(c.load(RELAXED) + count, RELAXED);
+        self.total_count
+            .store(self.total_count.load(RELAXED) + count, RELAXED);
+
+        if value != 0 && value < self.min_value.load(RELAXED) {
+            self.min_value.store(value, RELAXED);
+        }
+        if value > self.max_value.load(RELAXED) {
+            self.max_value.store(value, RELAXED);
+        }
+
+        true
+    }
+
+    fn record_values_atomic(&self, value: i64, count: i64) -> bool {
+        if value < 0 {
+            return false;
+        }
+
+        let counts_index = self.counts_index_for(value);
+        if counts_index < 0 || self.counts_len <= counts_index {
+            return false;
+        }
+
+        self.counts[self.normalize_index(counts_index) as usize].fetch_add(count, SEQ_CST);
+        self.total_count.fetch_add(count, SEQ_CST);
+
+        if value != 0 {
+            self.min_value.fetch_min(value, SEQ_CST);
+        }
+        self.max_value.fetch_max(value, SEQ_CST);
+
+        true
+    }
+
+    fn record_corrected_values(
+        &self,
+        value: i64,
+        count: i64,
+        expected_interval: i64,
+        record: impl Fn(&Self, i64, i64) -> bool,
+    ) -> bool {
+        if !record(self, value, count) {
+            return false;
+        }
+
+        if expected_interval <= 0 || value <= expected_interval {
+            return true;
+        }
+
+        let mut missing_value = value - expected_interval;
+        while missing_value >= expected_interval {
+            if !record(self, missing_value, count) {
+                return false;
+            }
+            missing_value -= expected_interval;
+        }
+
+        true
+    }
+
+    fn reset_internal_counters(&self) {
+        let mut min_non_zero_index = -1;
+        let mut max_index = -1;
+        let mut total_count = 0;
+
+        for i in 0..self.counts_len {
+            let count_at_index = self.count_at_index(i);
+            if count_at_index > 0 {
+                total_count += count_at_index;
+                max_index = i;
+                if min_non_zero_index == -1 && i != 0 {
+                    min_non_zero_index = i;
+                }
+            }
+        }
+
+        let max_value = if max_index == -1 {
+            0
+        } else {
+            self.value_at_index(max_index)
+        };
+        let min_value = if min_non_zero_index == -1 {
+            i64::MAX
+        } else {
+            self.value_at_index(min_non_zero_index)
+        };
+
+        self.max_value.store(max_value, RELAXED);
+        self.min_value.store(min_value, RELAXED);
+        self.total_count.store(total_count, RELAXED);
+    }
+
+    fn reset(&self) {
+        self.total_count.store(0, RELAXED);
+        self.min_value.store(i64::MAX, RELAXED);
+        self.max_value.store(0, RELAXED);
+        for c in self.counts.iter() {
+            c.store(0, RELAXED);
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        let mut iter = hdr_iter::new(self, Specifics::All);
+        let mut total = 0i64;
+
+        while iter.next() {
+            if iter.count != 0 {
+                total += iter.count * self.median_equivalent_value(iter.value);
+            }
+        }
+
+        total as f64 / self.total_count.load(RELAXED) as f64
+    }
+
+    fn stddev(&self) -> f64 {
+        let mean = self.mean();
+        let mut geometric_dev_total = 0.0;
+
+        let mut iter = hdr_iter::new(self, Specifics::All);
+        while iter.next() {
+            if iter.count != 0 {
+                let dev = self.median_equivalent_value(iter.value) as f64 - mean;
+                geometric_dev_total += (dev * dev) * iter.count as f64;
+            }
+        }
+
+        (geometric_dev_total / self.total_count.load(RELAXED) as f64).sqrt()
+    }
+
+    fn count_at_percentile(&self, percentile: f64) -> i64 {
+        let requested_percentile = percentile.min(100.0);
+        ((requested_percentile / 100.0) * self.total_count.load(RELAXED) as f64 + 0.5) as i64
+    }
+
+    fn value_at_percentile(&self, percentile: f64) -> i64 {
+        let count_at_percentile = self.count_at_percentile(percentile).max(1);
+
+        let mut count_to_idx = 0;
+        let mut value_from_idx = 0;
+        for idx in 0..self.counts_len {
+            count_to_idx += self.count_at_index(idx);
+            if count_to_idx >= count_at_percentile {
+                value_from_idx = self.value_at_index(idx);
+                break;
+            }
+        }
+
+        if percentile == 0.0 {
+            self.lowest_equivalent_value(value_from_idx)
+        } else {
+            self.highest_equivalent_value(value_from_idx)
+        }
+    }
+}
+
+pub unsafe fn hdr_init(
+    lowest_discernible_value: i64,
+    highest_trackable_value: i64,
+    significant_figures: i32,
+    result: *mut *mut hdr_histogram,
+) -> i32 {
+    let h = calculate_bucket_config(
+        lowest_discernible_value,
+        highest_trackable_value,
+        significant_figures,
+    )
+    .and_then(|cfg| hdr_histogram::new(&cfg));
+
+    match h {
+        Ok(h) => {
+            *result = Box::into_raw(h);
+            0
+        }
+        Err(err) => err,
+    }
+}
+
+pub unsafe fn hdr_close(hdr: *mut hdr_histogram) {
+    if !hdr.is_null() {
+        drop(Box::from_raw(hdr));
+    }
+}
+
+pub unsafe fn hdr_reset(hdr: *mut hdr_histogram) {
+    (*hdr).reset()
+}
+
+pub unsafe fn hdr_get_memory_size(hdr: *mut hdr_histogram) -> usize {
+    hdr_rust_memory_size((*hdr).counts_len)
+}
+
+pub unsafe fn hdr_record_value(hdr: *mut hdr_histogram, value: i64) -> bool {
+    (*hdr).record_values(value, 1)
+}
+
+pub unsafe fn hdr_record_value_atomic(hdr: *mut hdr_histogram, value: i64) -> bool {
+    (*hdr).record_values_atomic(value, 1)
+}
+
+pub unsafe fn hdr_record_values(hdr: *mut hdr_histogram, value: i64, count: i64) -> bool {
+    (*hdr).record_values(value, count)
+}
+
+pub unsafe fn hdr_record_values_atomic(hdr: *mut hdr_histogram, value: i64, count: i64) -> bool {
+    (*hdr).record_values_atomic(value, count)
+}
+
+pub unsafe fn hdr_record_corrected_value(
+    hdr: *mut hdr_histogram,
+    value: i64,
+    expected_interval: i64,
+) -> bool {
+    (*hdr).record_corrected_values(value, 1, expected_interval, hdr_histogram::record_values)
+}
+
+pub unsafe fn hdr_record_corrected_value_atomic(
+    hdr: *mut hdr_histogram,
+    value: i64,
+    expected_interval: i64,
+) -> bool {
+    (*hdr).record_corrected_values(
+        value,
+        1,
+        expected_interval,
+        hdr_histogram::record_values_atomic,
+    )
+}
+
+pub unsafe fn hdr_record_corrected_values(
+    hdr: *mut hdr_histogram,
+    value: i64,
+    count: i64,
+    expected_interval: i64,
+) -> bool {
+    (*hdr).record_corrected_values(
+        value,
+        count,
+        expected_interval,
+        hdr_histogram::record_values,
+    )
+}
+
+pub unsafe fn hdr_record_corrected_values_atomic(
+    hdr: *mut hdr_histogram,
+    value: i64,
+    count: i64,
+    expected_interval: i64,
+) -> bool {
+    (*hdr).record_corrected_values(
+        value,
+        count,
+        expected_interval,
+        hdr_histogram::record_values_atomic,
+    )
+}
+
+pub unsafe fn hdr_add(hdr: *mut hdr_histogram, other: *const hdr_histogram) -> i64 {
+    let h = &*hdr;
+    let mut iter = hdr_iter::new(&*other, Specifics::Recorded(0));
+    let mut dropped = 0;
+
+    while iter.next() {
+        if !h.record_values(iter.value, iter.count) {
+            dropped += iter.count;
+        }
+    }
+
+    dropped
+}
+
+pub unsafe fn hdr_add_while_correcting_for_coordinated_omission(
+    hdr: *mut hdr_histogram,
+    other: *mut hdr_histogram,
+    expected_interval: i64,
+) -> i64 {
+    let h = &*hdr;
+    let mut iter = hdr_iter::new(&*other, Specifics::Recorded(0));
+    let mut dropped = 0;
+
+    while iter.next() {
+        if !h.record_corrected_values(
+            iter.value,
+            iter.count,
+            expected_interval,
+            hdr_histogram::record_values,
+        ) {
+            dropped += iter.count;
+        }
+    }
+
+    dropped
+}
+
+pub unsafe fn hdr_min(hdr: *const hdr_histogram) -> i64 {
+    let h = &*hdr;
+    if h.count_at_index(0) > 0 {
+        return 0;
+    }
+
+    match h.min_value.load(RELAXED) {
+        i64::MAX => i64::MAX,
+        min => h.lowest_equivalent_value(min),
+    }
+}
+
+pub unsafe fn hdr_max(hdr: *const hdr_histogram) -> i64 {
+    let h = &*hdr;
+    match h.max_value.load(RELAXED) {
+        0 => 0,
+        max => h.highest_equivalent_value(max),
+    }
+}
+
+pub unsafe fn hdr_value_at_percentile(hdr: *const hdr_histogram, percentile: f64) -> i64 {
+    (*hdr).value_at_percentile(percentile)
+}
+
+pub unsafe fn hdr_value_at_percentiles(
+    hdr: *const hdr_histogram,
+    percentiles: *const f64,
+    values: *mut i64,
+    length: usize,
+) -> i32 {
+    if percentiles.is_null() || values.is_null() {
+        return libc::EINVAL;
+    }
+
+    let h = &*hdr;
+    let percentiles = slice::from_raw_parts(percentiles, length);
+
+    // As in the C implementation, `values` first holds the cumulative count needed for each
+    // percentile, which is then replaced by the value once it's reached. This assumes the
+    // percentiles are sorted.
+    for (i, &p) in percentiles.iter().enumerate() {
+        values.add(i).write(h.count_at_percentile(p).max(1));
+    }
+
+    let mut iter = hdr_iter::new(h, Specifics::All);
+    let mut total = 0;
+    let mut at_pos = 0;
+    while at_pos < length && iter.next() {
+        total += iter.count;
+        while at_pos < length && total >= values.add(at_pos).read() {
+            values
+                .add(at_pos)
+                .write(h.highest_equivalent_value(iter.value));
+            at_pos += 1;
+        }
+    }
+
+    0
+}
+
+pub unsafe fn hdr_stddev(hdr: *const hdr_histogram) -> f64 {
+    (*hdr).stddev()
+}
+
+pub unsafe fn hdr_mean(hdr: *const hdr_histogram) -> f64 {
+    (*hdr).mean()
+}
+
+pub unsafe fn hdr_count_at_value(hdr: *const hdr_histogram, value: i64) -> i64 {
+    let h = &*hdr;
+    let index = h.counts_index_for(value);
+    if index < 0 || index >= h.counts_len {
+        0
+    } else {
+        h.count_at_index(index)
+    }
+}
+
+pub unsafe fn hdr_count_at_index(hdr: *const hdr_histogram, index: i32) -> i64 {
+    (*hdr).count_at_index(index)
+}
+
+pub unsafe fn hdr_value_at_index(hdr: *const hdr_histogram, index: i32) -> i64 {
+    (*hdr).value_at_index(index)
+}
+
+pub unsafe fn hdr_values_are_equivalent(hdr: *const hdr_histogram, a: i64, b: i64) -> bool {
+    let h = &*hdr;
+    h.lowest_equivalent_value(a) == h.lowest_equivalent_value(b)
+}
+
+pub unsafe fn hdr_lowest_equivalent_value(hdr: *const hdr_histogram, value: i64) -> i64 {
+    (*hdr).lowest_equivalent_value(value)
+}
+
+pub unsafe fn hdr_next_non_equivalent_value(hdr: *const hdr_histogram, value: i64) -> i64 {
+    (*hdr).next_non_equivalent_value(value)
+}
+
+pub unsafe fn hdr_median_equivalent_value(hdr: *const hdr_histogram, value: i64) -> i64 {
+    (*hdr).median_equivalent_value(value)
+}
+
+// Iterators
+
+enum Specifics {
+    All,
+    Recorded(i64),
+    Linear {
+        count_added_in_this_iteration_step: i64,
+        value_units_per_bucket: i64,
+        next_value_reporting_level: i64,
+        next_value_reporting_level_lowest_equivalent: i64,
+    },
+    Log {
+        count_added_in_this_iteration_step: i64,
+        log_base: f64,
+        next_value_reporting_level: i64,
+        next_value_reporting_level_lowest_equivalent: i64,
+    },
+    Percentiles {
+        seen_last_value: bool,
+        ticks_per_half_distance: i32,
+        percentile_to_iterate_to: f64,
+        percentile: f64,
+    },
+}
+
+pub struct hdr_iter {
+    h: *const hdr_histogram,
+    counts_index: i32,
+    total_count: i64,
+    count: i64,
+    cumulative_count: i64,
+    value: i64,
+    highest_equivalent_value: i64,
+    lowest_equivalent_value: i64,
+    median_equivalent_value: i64,
+    value_iterated_from: i64,
+    value_iterated_to: i64,
+    specifics: Specifics,
+}
+
+impl hdr_iter {
+    fn new(h: &hdr_histogram, specifics: Specifics) -> Self {
+        hdr_iter {
+            h,
+            total_count: h.total_count.load(RELAXED),
+            specifics,
+            ..hdr_iter::empty()
+        }
+    }
+
+    /// An iterator which isn't attached to a histogram yet, as allocated by
+    /// `hdr_rust_iter_alloc`.
+    fn empty() -> Self {
+        hdr_iter {
+            h: ptr::null(),
+            counts_index: -1,
+            total_count: 0,
+            count: 0,
+            cumulative_count: 0,
+            value: 0,
+            highest_equivalent_value: 0,
+            lowest_equivalent_value: 0,
+            median_equivalent_value: 0,
+            value_iterated_from: 0,
+            value_iterated_to: 0,
+            specifics: Specifics::All,
+        }
+    }
+
+    /// The histogram being iterated. The caller of the `hdr_iter_*_init` function guarantees it
+    /// outlives the iterator.
+    fn h<'a>(&self) -> &'a hdr_histogram {
+        unsafe { &*self.h }
+    }
+
+    fn has_buckets(&self) -> bool {
+        self.counts_index < self.h().counts_len
+    }
+
+    fn has_next(&self) -> bool {
+        self.cumulative_count < self.total_count
+    }
+
+    fn move_next(&mut self) -> bool {
+        let h = self.h();
+
+        self.counts_index += 1;
+        if !self.has_buckets() {
+            return false;
+        }
+
+        self.count = h.count_at_index(self.counts_index);
+        self.cumulative_count += self.count;
+
+        let value = h.value_at_index(self.counts_index);
+        let bucket_index = h.bucket_index(value);
+        let sub_bucket_index = sub_bucket_index(value, bucket_index, h.unit_magnitude);
+        let leq = value_from_index(bucket_index, sub_bucket_index, h.unit_magnitude);
+        let size_of_equivalent_value_range =
+            h.size_of_equivalent_value_range_given_bucket_indices(bucket_index, sub_bucket_index);
+
+        self.lowest_equivalent_value = leq;
+        self.value = value;
+        self.highest_equivalent_value = leq + size_of_equivalent_value_range - 1;
+        self.median_equivalent_value = leq + (size_of_equivalent_value_range >> 1);
+
+        true
+    }
+
+    fn next_value_greater_than_reporting_level_upper_bound(
+        &self,
+        reporting_level_upper_bound: i64,
+    ) -> bool {
+        if self.counts_index >= self.h().counts_len {
+            return false;
+        }
+
+        self.h().value_at_index(self.counts_index + 1) > reporting_level_upper_bound
+    }
+
+    fn basic_next(&mut self) -> bool {
+        self.has_next() && self.move_next()
+    }
+
+    fn update_iterated_values(&mut self, new_value_iterated_to: i64) {
+        self.value_iterated_from = self.value_iterated_to;
+        self.value_iterated_to = new_value_iterated_to;
+    }
+
+    fn next(&mut self) -> bool {
+        match self.specifics {
+            Specifics::All => self.all_values_next(),
+            Specifics::Recorded(_) => self.recorded_next(),
+            Specifics::Linear { .. } | Specifics::Log { .. } => self.reporting_level_next(),
+            Specifics::Percentiles { .. } => self.percentile_next(),
+        }
+    }
+
+    fn all_values_next(&mut self) -> bool {
+        let result = self.move_next();
+        if result {
+            self.update_iterated_values(self.value);
+        }
+        result
+    }
+
+    fn recorded_next(&mut self) -> bool {
+        while self.basic_next() {
+            if self.count != 0 {
+                self.update_iterated_values(self.value);
+                self.specifics = Specifics::Recorded(self.count);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Shared by linear and logarithmic iteration, which only differ in how the next reporting
+    /// level is computed.
+    fn reporting_level_next(&mut self) -> bool {
+        let h = self.h();
+
+        let lowest_equivalent = match &mut self.specifics {
+            Specifics::Linear {
+                count_added_in_this_iteration_step,
+                next_value_reporting_level_lowest_equivalent,
+                ..
+            }
+            | Specifics::Log {
+                count_added_in_this_iteration_step,
+                next_value_reporting_level_lowest_equivalent,
+                ..
+            } => {
+                *count_added_in_this_iteration_step = 0;
+                *next_value_reporting_level_lowest_equivalent
+            }
+            _ => unreachable!(),
+        };
+
+        if !self.has_next()
+            && !self.next_value_greater_than_reporting_level_upper_bound(lowest_equivalent)
+        {
+            return false;
+        }
+
+        loop {
+            let (level, lowest_equivalent) = match self.specifics {
+                Specifics::Linear {
+                    next_value_reporting_level,
+                    next_value_reporting_level_lowest_equivalent,
+                    ..
+                }
+                | Specifics::Log {
+                    next_value_reporting_level,
+                    next_value_reporting_level_lowest_equivalent,
+                    ..
+                } => (
+                    next_value_reporting_level,
+                    next_value_reporting_level_lowest_equivalent,
+                ),
+                _ => unreachable!(),
+            };
+
+            if self.value >= lowest_equivalent {
+                self.update_iterated_values(level);
+
+                match &mut self.specifics {
+                    Specifics::Linear {
+                        value_units_per_bucket,
+                        next_value_reporting_level,
+                        next_value_reporting_level_lowest_equivalent,
+                        ..
+                    } => {
+                        *next_value_reporting_level += *value_units_per_bucket;
+                        *next_value_reporting_level_lowest_equivalent =
+                            h.lowest_equivalent_value(*next_value_reporting_level);
+                    }
+                    Specifics::Log {
+                        log_base,
+                        next_value_reporting_level,
+                        next_value_reporting_level_lowest_equivalent,
+                        ..
+                    } => {
+                        *next_value_reporting_level *= *log_base as i64;
+                        *next_value_reporting_level_lowest_equivalent =
+                            h.lowest_equivalent_value(*next_value_reporting_level);
+                    }
+                    _ => unreachable!(),
+                }
+
+                return true;
+            }
+
+            if !self.move_next() {
+                return true;
+            }
+
+            match &mut self.specifics {
+                Specifics::Linear {
+                    count_added_in_this_iteration_step,
+                    ..
+                }
+                | Specifics::Log {
+                    count_added_in_this_iteration_step,
+                    ..
+                } => *count_added_in_this_iteration_step += self.count,
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    fn percentile_next(&mut self) -> bool {
+        let h = self.h();
+
+        if !self.has_next() {
+            if let Specifics::Percentiles {
+                seen_last_value,
+                percentile,
+                ..
+            } = &mut self.specifics
+            {
+                if *seen_last_value {
+                    return false;
+                }
+                *seen_last_value = true;
+                *percentile = 100.0;
+            }
+            return true;
+        }
+
+        if self.counts_index == -1 && !self.basic_next() {
+            return false;
+        }
+
+        loop {
+            let current_percentile =
+                (100.0 * self.cumulative_count as f64) / h.total_count.load(RELAXED) as f64;
+
+            if let Specifics::Percentiles {
+                ticks_per_half_distance,
+                percentile_to_iterate_to,
+                ..
+            } = self.specifics
+            {
+                if self.count != 0 && percentile_to_iterate_to <= current_percentile {
+                    self.update_iterated_values(h.highest_equivalent_value(self.value));
+
+                    let temp =
+                        ((100.0 / (100.0 - percentile_to_iterate_to)).ln() / 2f64.ln()) as i64;
+                    let half_distance = 2f64.powf(temp.saturating_add(1) as f64) as i64;
+                    let percentile_reporting_ticks =
+                        (ticks_per_half_distance as i64).saturating_mul(half_distance);
+
+                    self.specifics = Specifics::Percentiles {
+                        seen_last_value: false,
+                        ticks_per_half_distance,
+                        percentile_to_iterate_to: percentile_to_iterate_to
+                            + 100.0 / percentile_reporting_ticks as f64,
+                        percentile: percentile_to_iterate_to,
+                    };
+
+                    return true;
+                }
+            }
+
+            if !self.basic_next() {
+                return true;
+            }
+        }
+    }
+}
+
+pub unsafe fn hdr_iter_recorded_init(iter: *mut hdr_iter, hdr: *const hdr_histogram) {
+    iter.write(hdr_iter::new(&*hdr, Specifics::Recorded(0)));
+}
+
+pub unsafe fn hdr_iter_linear_init(
+    iter: *mut hdr_iter,
+    hdr: *const hdr_histogram,
+    value_units_per_bucket: i64,
+) {
+    let h = &*hdr;
+    iter.write(hdr_iter::new(
+        h,
+        Specifics::Linear {
+            count_added_in_this_iteration_step: 0,
+            value_units_per_bucket,
+            next_value_reporting_level: value_units_per_bucket,
+            next_value_reporting_level_lowest_equivalent: h
+                .lowest_equivalent_value(value_units_per_bucket),
+        },
+    ));
+}
+
+pub unsafe fn hdr_iter_log_init(
+    iter: *mut hdr_iter,
+    hdr: *const hdr_histogram,
+    value_units_first_bucket: i64,
+    log_base: f64,
+) {
+    let h = &*hdr;
+    iter.write(hdr_iter::new(
+        h,
+        Specifics::Log {
+            count_added_in_this_iteration_step: 0,
+            log_base,
+            next_value_reporting_level: value_units_first_bucket,
+            next_value_reporting_level_lowest_equivalent: h
+                .lowest_equivalent_value(value_units_first_bucket),
+        },
+    ));
+}
+
+pub unsafe fn hdr_iter_percentile_init(
+    iter: *mut hdr_iter,
+    hdr: *const hdr_histogram,
+    ticks_per_half_distance: i32,
+) {
+    iter.write(hdr_iter::new(
+        &*hdr,
+        Specifics::Percentiles {
+            seen_last_value: false,
+            ticks_per_half_distance,
+            percentile_to_iterate_to: 0.0,
+            percentile: 0.0,
+        },
+    ));
+}
+
+pub unsafe fn hdr_iter_next(iter: *mut hdr_iter) -> bool {
+    (*iter).next()
+}
+
+// Writer/reader phaser
+
+pub struct hdr_writer_reader_phaser {
+    start_epoch: AtomicI64,
+    even_end_epoch: AtomicI64,
+    odd_end_epoch: AtomicI64,
+    reader_locked: AtomicBool,
+}
+
+impl hdr_writer_reader_phaser {
+    fn new() -> Self {
+        hdr_writer_reader_phaser {
+            start_epoch: AtomicI64::new(0),
+            even_end_epoch: AtomicI64::new(0),
+            odd_end_epoch: AtomicI64::new(i64::MIN),
+            reader_locked: AtomicBool::new(false),
+        }
+    }
+}
+
+pub unsafe fn hdr_writer_reader_phaser_init(p: *mut hdr_writer_reader_phaser) -> i32 {
+    let p = &*p;
+    p.start_epoch.store(0, SEQ_CST);
+    p.even_end_epoch.store(0, SEQ_CST);
+    p.odd_end_epoch.store(i64::MIN, SEQ_CST);
+    p.reader_locked.store(false, SEQ_CST);
+    0
+}
+
+pub unsafe fn hdr_writer_reader_phaser_destroy(_p: *mut hdr_writer_reader_phaser) {}
+
+pub unsafe fn hdr_phaser_writer_enter(p: *mut hdr_writer_reader_phaser) -> i64 {
+    (*p).start_epoch.fetch_add(1, SEQ_CST).wrapping_add(1)
+}
+
+pub unsafe fn hdr_phaser_writer_exit(
+    p: *mut hdr_writer_reader_phaser,
+    critical_value_at_enter: i64,
+) {
+    let p = &*p;
+    let end_epoch = if critical_value_at_enter < 0 {
+        &p.odd_end_epoch
+    } else {
+        &p.even_end_epoch
+    };
+    end_epoch.fetch_add(1, SEQ_CST);
+}
+
+pub unsafe fn hdr_phaser_reader_lock(p: *mut hdr_writer_reader_phaser) {
+    // Readers are rare and hold the lock briefly, so spinning is adequate.
+    while (*p)
+        .reader_locked
+        .compare_exchange_weak(false, true, Ordering::Acquire, RELAXED)
+        .is_err()
+    {
+        thread::yield_now();
+    }
+}
+
+pub unsafe fn hdr_phaser_reader_unlock(p: *mut hdr_writer_reader_phaser) {
+    (*p).reader_locked.store(false, Ordering::Release);
+}
+
+pub unsafe fn hdr_phaser_flip_phase(p: *mut hdr_writer_reader_phaser, sleep_time_ns: i64) {
+    let p = &*p;
+
+    let next_phase_is_even = p.start_epoch.load(SEQ_CST) < 0;
+    let initial_start_value = if next_phase_is_even {
+        p.even_end_epoch.store(0, SEQ_CST);
+        0
+    } else {
+        p.odd_end_epoch.store(i64::MIN, SEQ_CST);
+        i64::MIN
+    };
+
+    let start_value_at_flip = p.start_epoch.swap(initial_start_value, SEQ_CST);
+
+    loop {
+        let end_epoch = if next_phase_is_even {
+            p.odd_end_epoch.load(SEQ_CST)
+        } else {
+            p.even_end_epoch.load(SEQ_CST)
+        };
+
+        if end_epoch == start_value_at_flip {
+            break;
+        }
+
+        if sleep_time_ns == 0 {
+            thread::yield_now();
+        } else {
+            thread::sleep(Duration::from_nanos(sleep_time_ns as u64));
+        }
+    }
+}
+
+// Interval recorder
+
+pub struct hdr_interval_recorder {
+    active: AtomicPtr<hdr_histogram>,
+    phaser: hdr_writer_reader_phaser,
+}
+
+pub unsafe fn hdr_interval_recorder_destroy(r: *mut hdr_interval_recorder) {
+    let r = &mut *r;
+    hdr_writer_reader_phaser_destroy(&mut r.phaser);
+    hdr_close(mem::replace(r.active.get_mut(), ptr::null_mut()));
+}
+
+/// Run `record` on the active histogram inside a writer critical section.
+unsafe fn interval_recorder_record(
+    r: *mut hdr_interval_recorder,
+    record: impl FnOnce(*mut hdr_histogram) -> bool,
+) -> bool {
+    // Other writers and the reader share the recorder, so only ever take shared references to
+    // it: everything they update concurrently is atomic.
+    let phaser = ptr::addr_of!((*r).phaser) as *mut hdr_writer_reader_phaser;
+
+    let critical_value = hdr_phaser_writer_enter(phaser);
+    let result = record((*r).active.load(SEQ_CST));
+    hdr_phaser_writer_exit(phaser, critical_value);
+
+    result
+}
+
+pub unsafe fn hdr_interval_recorder_record_value_atomic(
+    r: *mut hdr_interval_recorder,
+    value: i64,
+) -> bool {
+    interval_recorder_record(r, |h| hdr_record_value_atomic(h, value))
+}
+
+pub unsafe fn hdr_interval_recorder_record_values_atomic(
+    r: *mut hdr_interval_recorder,
+    value: i64,
+    count: i64,
+) -> bool {
+    interval_recorder_record(r, |h| hdr_record_values_atomic(h, value, count))
+}
+
+pub unsafe fn hdr_interval_recorder_record_corrected_value_atomic(
+    r: *mut hdr_interval_recorder,
+    value: i64,
+    expected_interval: i64,
+) -> bool {
+    interval_recorder_record(r, |h| {
+        hdr_record_corrected_value_atomic(h, value, expected_interval)
+    })
+}
+
+pub unsafe fn hdr_interval_recorder_record_corrected_values_atomic(
+    r: *mut hdr_interval_recorder,
+    value: i64,
+    count: i64,
+    expected_interval: i64,
+) -> bool {
+    interval_recorder_record(r, |h| {
+        hdr_record_corrected_values_atomic(h, value, count, expected_interval)
+    })
+}
+
+pub unsafe fn hdr_interval_recorder_sample_and_recycle(
+    r: *mut hdr_interval_recorder,
+    histogram_to_recycle: *mut hdr_histogram,
+) -> *mut hdr_histogram {
+    let r = &*r;
+    let phaser = ptr::addr_of!(r.phaser) as *mut hdr_writer_reader_phaser;
+    let mut recycle = histogram_to_recycle;
+
+    if recycle.is_null() {
+        let active = &*r.active.load(SEQ_CST);
+        let res = hdr_init(
+            active.lowest_discernible_value,
+            active.highest_trackable_value,
+            active.significant_figures,
+            &mut recycle,
+        );
+        if res != 0 {
+            return ptr::null_mut();
+        }
+    }
+
+    hdr_phaser_reader_lock(phaser);
+    // Writers only reach the active histogram, so the inactive one being recycled is ours alone.
+    hdr_reset(recycle);
+    let old_active = r.active.swap(recycle, SEQ_CST);
+    hdr_phaser_flip_phase(phaser, 0);
+    hdr_phaser_reader_unlock(phaser);
+
+    old_active
+}
+
+pub unsafe fn hdr_strerror(err: i32) -> *const c_char {
+    let s: &'static [u8] = match err {
+        HDR_COMPRESSION_COOKIE_MISMATCH => b"Compression cookie mismatch\0",
+        HDR_ENCODING_COOKIE_MISMATCH => b"Encoding cookie mismatch\0",
+        HDR_DEFLATE_INIT_FAIL => b"Deflate initialisation failed\0",
+        HDR_DEFLATE_FAIL => b"Deflate failed\0",
+        HDR_INFLATE_INIT_FAIL => b"Inflate initialisation failed\0",
+        HDR_INFLATE_FAIL => b"Inflate failed\0",
+        HDR_LOG_INVALID_VERSION => b"Invalid log version\0",
+        HDR_TRAILING_ZEROS_INVALID => b"Invalid number of trailing zeros\0",
+        HDR_VALUE_TRUNCATED => b"Truncated value found when decoding\0",
+        HDR_ENCODED_INPUT_TOO_LONG => b"The encoded input exceeds the size of the histogram\0",
+        _ => return libc::strerror(err),
+    };
+
+    s.as_ptr() as *const c_char
+}
+
+// Equivalents of the C accessor glue in glue.c
+
+pub unsafe fn hdr_rust_total_count(hdr: *const hdr_histogram) -> i64 {
+    (*hdr).total_count.load(RELAXED)
+}
+
+pub unsafe fn hdr_rust_counts_len(hdr: *const hdr_histogram) -> i64 {
+    (*hdr).counts_len as i64
+}
+
+pub unsafe fn hdr_rust_lowest_discernible_value(hdr: *const hdr_histogram) -> i64 {
+    (*hdr).lowest_discernible_value
+}
+
+pub unsafe fn hdr_rust_highest_trackable_value(hdr: *const hdr_histogram) -> i64 {
+    (*hdr).highest_trackable_value
+}
+
+pub unsafe fn hdr_rust_significant_figures(hdr: *const hdr_histogram) -> i32 {
+    (*hdr).significant_figures
+}
+
+pub unsafe fn hdr_rust_unit_magnitude(hdr: *const hdr_histogram) -> i32 {
+    (*hdr).unit_magnitude
+}
+
+pub unsafe fn hdr_rust_sub_bucket_count(hdr: *const hdr_histogram) -> i32 {
+    (*hdr).sub_bucket_count
+}
+
+pub unsafe fn hdr_rust_bucket_count(hdr: *const hdr_histogram) -> i32 {
+    (*hdr).bucket_count
+}
+
+pub unsafe fn hdr_rust_normalizing_index_offset(hdr: *const hdr_histogram) -> i32 {
+    (*hdr).normalizing_index_offset
+}
+
+pub unsafe fn hdr_rust_conversion_ratio(hdr: *const hdr_histogram) -> f64 {
+    (*hdr).conversion_ratio
+}
+
+pub unsafe fn hdr_rust_set_normalizing_index_offset(hdr: *mut hdr_histogram, offset: i32) {
+    (*hdr).normalizing_index_offset = offset;
+}
+
+pub unsafe fn hdr_rust_set_conversion_ratio(hdr: *mut hdr_histogram, ratio: f64) {
+    (*hdr).conversion_ratio = ratio;
+}
+
+pub unsafe fn hdr_rust_get_count_at_index(hdr: *const hdr_histogram, index: i32) -> i64 {
+    (*hdr).counts[index as usize].load(RELAXED)
+}
+
+pub unsafe fn hdr_rust_set_count_at_index(hdr: *mut hdr_histogram, index: i32, count: i64) {
+    (*hdr).counts[index as usize].store(count, RELAXED);
+}
+
+pub unsafe fn hdr_rust_calculate_bucket_config(
+    lowest_discernible_value: i64,
+    highest_trackable_value: i64,
+    significant_figures: i32,
+    unit_magnitude: *mut i32,
+    sub_bucket_count: *mut i32,
+    bucket_count: *mut i32,
+    counts_len: *mut i32,
+) -> i32 {
+    match calculate_bucket_config(
+        lowest_discernible_value,
+        highest_trackable_value,
+        significant_figures,
+    ) {
+        Ok(cfg) => {
+            *unit_magnitude = cfg.unit_magnitude;
+            *sub_bucket_count = cfg.sub_bucket_count;
+            *bucket_count = cfg.bucket_count;
+            *counts_len = cfg.counts_len;
+            0
+        }
+        Err(err) => err,
+    }
+}
+
+pub unsafe fn hdr_rust_memory_size(counts_len: i32) -> usize {
+    mem::size_of::<hdr_histogram>() + counts_len as usize * mem::size_of::<i64>()
+}
+
+pub unsafe fn hdr_rust_resize(
+    hdr: *const hdr_histogram,
+    highest_trackable_value: i64,
+    result: *mut *mut hdr_histogram,
+) -> i32 {
+    let h = &*hdr;
+
+    let cfg = match calculate_bucket_config(
+        h.lowest_discernible_value,
+        highest_trackable_value,
+        h.significant_figures,
+    ) {
+        Ok(cfg) => cfg,
+        Err(err) => return err,
+    };
+    let mut histogram = match hdr_histogram::new(&cfg) {
+        Ok(histogram) => histogram,
+        Err(err) => return err,
+    };
+
+    for i in 0..h.counts_len.min(histogram.counts_len) {
+        *histogram.counts[i as usize].get_mut() = h.count_at_index(i);
+    }
+    *histogram.total_count.get_mut() = h.total_count.load(RELAXED);
+    *histogram.min_value.get_mut() = h.min_value.load(RELAXED);
+    *histogram.max_value.get_mut() = h.max_value.load(RELAXED);
+    histogram.conversion_ratio = h.conversion_ratio;
+
+    *result = Box::into_raw(histogram);
+    0
+}
+
+pub unsafe fn hdr_rust_clone(hdr: *const hdr_histogram) -> *mut hdr_histogram {
+    Box::into_raw((*hdr).copy())
+}
+
+pub unsafe fn hdr_rust_reset_internal_counters(hdr: *mut hdr_histogram) {
+    (*hdr).reset_internal_counters()
+}
+
+pub unsafe fn hdr_rust_iter_alloc() -> *mut hdr_iter {
+    Box::into_raw(Box::new(hdr_iter::empty()))
+}
+
+pub unsafe fn hdr_rust_iter_free(iter: *mut hdr_iter) {
+    drop(Box::from_raw(iter))
+}
+
+pub unsafe fn hdr_rust_iter_count(iter: *const hdr_iter) -> i64 {
+    (*iter).count
+}
+
+pub unsafe fn hdr_rust_iter_cumulative_count(iter: *const hdr_iter) -> i64 {
+    (*iter).cumulative_count
+}
+
+pub unsafe fn hdr_rust_iter_value(iter: *const hdr_iter) -> i64 {
+    (*iter).value
+}
+
+pub unsafe fn hdr_rust_iter_highest_equivalent_value(iter: *const hdr_iter) -> i64 {
+    (*iter).highest_equivalent_value
+}
+
+pub unsafe fn hdr_rust_iter_lowest_equivalent_value(iter: *const hdr_iter) -> i64 {
+    (*iter).lowest_equivalent_value
+}
+
+pub unsafe fn hdr_rust_iter_median_equivalent_value(iter: *const hdr_iter) -> i64 {
+    (*iter).median_equivalent_value
+}
+
+pub unsafe fn hdr_rust_iter_value_iterated_from(iter: *const hdr_iter) -> i64 {
+    (*iter).value_iterated_from
+}
+
+pub unsafe fn hdr_rust_iter_value_iterated_to(iter: *const hdr_iter) -> i64 {
+    (*iter).value_iterated_to
+}
+
+pub unsafe fn hdr_rust_iter_recorded_count_added(iter: *const hdr_iter) -> i64 {
+    match (*iter).specifics {
+        Specifics::Recorded(count_added_in_this_iteration_step) => {
+            count_added_in_this_iteration_step
+        }
+        _ => 0,
+    }
+}
+
+pub unsafe fn hdr_rust_iter_linear_count_added(iter: *const hdr_iter) -> i64 {
+    match (*iter).specifics {
+        Specifics::Linear {
+            count_added_in_this_iteration_step,
+            ..
+        } => count_added_in_this_iteration_step,
+        _ => 0,
+    }
+}
+
+pub unsafe fn hdr_rust_iter_log_count_added(iter: *const hdr_iter) -> i64 {
+    match (*iter).specifics {
+        Specifics::Log {
+            count_added_in_this_iteration_step,
+            ..
+        } => count_added_in_this_iteration_step,
+        _ => 0,
+    }
+}
+
+pub unsafe fn hdr_rust_iter_percentile(iter: *const hdr_iter) -> f64 {
+    match (*iter).specifics {
+        Specifics::Percentiles { percentile, .. } => percentile,
+        _ => 0.0,
+    }
+}
+
+pub unsafe fn hdr_rust_interval_recorder_alloc() -> *mut hdr_interval_recorder {
+    Box::into_raw(Box::new(hdr_interval_recorder {
+        active: AtomicPtr::new(ptr::null_mut()),
+        phaser: hdr_writer_reader_phaser::new(),
+    }))
+}
+
+pub unsafe fn hdr_rust_interval_recorder_init_all(
+    r: *mut hdr_interval_recorder,
+    lowest_discernible_value: i64,
+    highest_trackable_value: i64,
+    significant_figures: i32,
+) -> i32 {
+    let r = &mut *r;
+
+    let res = hdr_writer_reader_phaser_init(&mut r.phaser);
+    if res != 0 {
+        return res;
+    }
+
+    let res = hdr_init(
+        lowest_discernible_value,
+        highest_trackable_value,
+        significant_figures,
+        r.active.get_mut(),
+    );
+    if res != 0 {
+        hdr_writer_reader_phaser_destroy(&mut r.phaser);
+    }
+    res
+}
+
+pub unsafe fn hdr_rust_interval_recorder_free(r: *mut hdr_interval_recorder) {
+    drop(Box::from_raw(r))
+}
+
+pub unsafe fn hdr_rust_phaser_alloc() -> *mut hdr_writer_reader_phaser {
+    Box::into_raw(Box::new(hdr_writer_reader_phaser::new()))
+}
+
+pub unsafe fn hdr_rust_phaser_free(p: *mut hdr_writer_reader_phaser) {
+    drop(Box::from_raw(p))
+}
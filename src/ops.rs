@@ -0,0 +1,121 @@
+//! Operator and iterator trait implementations for `Histogram`.
+//!
+//! These are conveniences over `try_add`, `subtract` and `record_value`. The operators can't
+//! return errors, so they panic where those methods would fail. `Sum`, `FromIterator` and
+//! `Extend` grow the histogram they accumulate into instead, so they never drop values in range
+//! of their inputs.
+
+use std::{
+    iter::{FromIterator, Sum},
+    ops::{Add, AddAssign, SubAssign},
+};
+
+use crate::{Histogram, HistogramConfig};
+
+/// Adds the counts of `rhs`, as `try_add`. The configurations may differ, in which case the values
+/// of `rhs` are recorded at this histogram's precision.
+///
+/// # Panics
+///
+/// If any counts of `rhs` are out of range and dropped.
+impl AddAssign<&Histogram> for Histogram {
+    fn add_assign(&mut self, rhs: &Histogram) {
+        if let Err(err) = self.try_add(rhs) {
+            panic!("Histogram add: {}", err)
+        }
+    }
+}
+
+/// Adds the counts of `rhs`, as `try_add`.
+///
+/// # Panics
+///
+/// If any counts of `rhs` are out of range and dropped.
+impl Add<&Histogram> for Histogram {
+    type Output = Histogram;
+
+    fn add(mut self, rhs: &Histogram) -> Histogram {
+        self += rhs;
+        self
+    }
+}
+
+/// Subtracts the counts of `rhs`, as `subtract`.
+///
+/// # Panics
+///
+/// If the histograms are incompatible, or any count would become negative.
+impl SubAssign<&Histogram> for Histogram {
+    fn sub_assign(&mut self, rhs: &Histogram) {
+        if let Err(err) = self.subtract(rhs) {
+            panic!("Histogram subtract: {}", err)
+        }
+    }
+}
+
+/// Merge histograms into one with the [`covering`](struct.HistogramConfig.html#method.covering)
+/// configuration of them all, so no counts are dropped. The result auto-resizes if any of them
+/// do. An empty iterator produces an empty histogram with the default
+/// [`HistogramConfig`](struct.HistogramConfig.html).
+///
+/// ```
+/// # use hdrhistogram_c::Histogram;
+/// let mut shards = Vec::new();
+/// for i in 1..=4 {
+///     let mut h = Histogram::new(1, 1000 * i, 3).unwrap();
+///     h.extend(vec![i * 500; 5]);
+///     shards.push(h);
+/// }
+///
+/// let total: Histogram = shards.iter().sum();
+/// assert_eq!(total.total_count(), 20);
+/// assert_eq!(total.max(), 2000);
+/// assert_eq!(total.highest_trackable_value(), 4000);
+/// ```
+impl<'a> Sum<&'a Histogram> for Histogram {
+    fn sum<I: Iterator<Item = &'a Histogram>>(iter: I) -> Histogram {
+        let histograms: Vec<&Histogram> = iter.collect();
+
+        let config = HistogramConfig::covering(histograms.iter().copied());
+        let mut sum = config
+            .auto_resize(true)
+            .build()
+            .expect("Histogram allocation failed");
+        for &h in &histograms {
+            // The inherent add, not the `Add` operator, which would consume `sum`
+            Histogram::add(&mut sum, h);
+        }
+        // Only the default configuration of an empty iterator covers with auto-resizing
+        sum.set_auto_resize(
+            config.is_auto_resize() || histograms.iter().any(|h| h.is_auto_resize()),
+        );
+        sum
+    }
+}
+
+/// Merge histograms, as `Sum<&Histogram>`.
+impl Sum<Histogram> for Histogram {
+    fn sum<I: Iterator<Item = Histogram>>(iter: I) -> Histogram {
+        iter.collect::<Vec<_>>().iter().sum()
+    }
+}
+
+/// Merge histograms, as `Sum<Histogram>`.
+impl FromIterator<Histogram> for Histogram {
+    fn from_iter<I: IntoIterator<Item = Histogram>>(iter: I) -> Histogram {
+        iter.into_iter().sum()
+    }
+}
+
+/// Record each value, growing the histogram to fit values above its highest trackable value as
+/// auto-resizing does. Values which can't be recorded at all, such as negative ones, are skipped.
+impl Extend<i64> for Histogram {
+    fn extend<I: IntoIterator<Item = i64>>(&mut self, iter: I) {
+        let auto_resize = self.is_auto_resize();
+        self.set_auto_resize(true);
+        for value in iter {
+            self.record_value(value);
+        }
+        self.set_auto_resize(auto_resize);
+    }
+}
@@ -0,0 +1,105 @@
+//! Percentile distribution reports, as printed by `hdr_percentiles_print`.
+
+use std::io::{self, Write};
+
+use crate::Histogram;
+
+/// Output format for [`Histogram::write_percentiles`](struct.Histogram.html#method.write_percentiles).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PercentilesFormat {
+    /// Fixed width columns followed by a summary footer, as produced by HdrHistogram's Java
+    /// `outputPercentileDistribution`. This is the format the HdrHistogram plotter accepts.
+    Classic,
+    /// Comma separated values, without the footer.
+    Csv,
+}
+
+impl Histogram {
+    /// Write the percentile distribution of the histogram as a table of value, percentile, total
+    /// count and `1/(1-percentile)`, in the same format as `hdr_percentiles_print`.
+    ///
+    /// `ticks_per_half_distance` is as for `percentile_iter`. Values are divided by `value_scale`,
+    /// for example 1000.0 to report microsecond values in milliseconds, and printed with as many
+    /// decimal places as the histogram has significant figures.
+    ///
+    /// ```
+    /// # use hdrhistogram_c::{Histogram, PercentilesFormat};
+    /// let mut h = Histogram::new(1, 1000000, 3).unwrap();
+    /// h.record_values(1000, 3);
+    /// h.record_value(2000);
+    ///
+    /// let mut out = Vec::new();
+    /// h.write_percentiles(&mut out, 1, 1.0, PercentilesFormat::Csv).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "Value,Percentile,TotalCount,1/(1-Percentile)\n\
+    ///      1000.000,0.000000,3,1.00\n\
+    ///      1000.000,0.500000,3,2.00\n\
+    ///      1000.000,0.750000,3,4.00\n\
+    ///      2000.000,0.875000,4,8.00\n\
+    ///      2000.000,1.000000,4,inf\n"
+    /// );
+    /// ```
+    pub fn write_percentiles(
+        &self,
+        writer: &mut impl Write,
+        ticks_per_half_distance: i32,
+        value_scale: f64,
+        format: PercentilesFormat,
+    ) -> io::Result<()> {
+        let precision = self.significant_figures() as usize;
+
+        match format {
+            PercentilesFormat::Classic => writeln!(
+                writer,
+                "{:>12} {:>12} {:>12} {:>12}\n",
+                "Value", "Percentile", "TotalCount", "1/(1-Percentile)"
+            )?,
+            PercentilesFormat::Csv => {
+                writeln!(writer, "Value,Percentile,TotalCount,1/(1-Percentile)")?
+            }
+        }
+
+        for item in self.percentile_iter(ticks_per_half_distance) {
+            let value = item.highest_equivalent_value as f64 / value_scale;
+            let percentile = item.percentile / 100.0;
+            let inverted_percentile = 1.0 / (1.0 - percentile);
+
+            match format {
+                PercentilesFormat::Classic => writeln!(
+                    writer,
+                    "{:12.*} {:12.6} {:12} {:12.2}",
+                    precision, value, percentile, item.cumulative_count, inverted_percentile
+                )?,
+                PercentilesFormat::Csv => writeln!(
+                    writer,
+                    "{:.*},{:.6},{},{:.2}",
+                    precision, value, percentile, item.cumulative_count, inverted_percentile
+                )?,
+            }
+        }
+
+        if format == PercentilesFormat::Classic {
+            writeln!(
+                writer,
+                "#[Mean    = {:12.3}, StdDeviation   = {:12.3}]",
+                self.mean() / value_scale,
+                self.stddev() / value_scale
+            )?;
+            writeln!(
+                writer,
+                "#[Max     = {:12.3}, Total count    = {:12}]",
+                self.max() as f64 / value_scale,
+                self.total_count()
+            )?;
+            writeln!(
+                writer,
+                "#[Buckets = {:12}, SubBuckets     = {:12}]",
+                self.bucket_count(),
+                self.sub_bucket_count()
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+//! Writer/reader phaser, wrapping `hdr_writer_reader_phaser`.
+
+use std::marker::PhantomData;
+
+use crate::{ffi, HistogramErr};
+
+/// A synchronization primitive for double-buffered data structures, allowing wait-free writers to
+/// coexist with a reader which periodically swaps the buffers.
+///
+/// Writers wrap each update in a critical section with
+/// [`writer_enter`](#method.writer_enter). The reader takes the
+/// [`reader_lock`](#method.reader_lock), swaps the active buffer out, then calls
+/// [`flip_phase`](struct.PhaserReaderGuard.html#method.flip_phase), which waits until all writers
+/// which may have seen the old buffer have left their critical sections. The old buffer is then
+/// safe to read. This is the mechanism `IntervalRecorder` uses.
+///
+/// The phaser doesn't hold the data itself, so the buffers and the "active" selector need to be
+/// shareable, typically with atomics.
+///
+/// ```
+/// # use hdrhistogram_c::WriterReaderPhaser;
+/// use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+///
+/// let phaser = WriterReaderPhaser::new().unwrap();
+/// let active = AtomicUsize::new(0);
+/// let counters = [AtomicU64::new(0), AtomicU64::new(0)];
+///
+/// // Writer
+/// {
+///     let _guard = phaser.writer_enter();
+///     counters[active.load(Ordering::SeqCst)].fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// // Reader
+/// let mut reader = phaser.reader_lock();
+/// let inactive = active.fetch_xor(1, Ordering::SeqCst);
+/// reader.flip_phase(0);
+/// assert_eq!(counters[inactive].swap(0, Ordering::Relaxed), 1);
+/// ```
+pub struct WriterReaderPhaser(*mut ffi::hdr_writer_reader_phaser);
+
+unsafe impl Send for WriterReaderPhaser {}
+unsafe impl Sync for WriterReaderPhaser {}
+
+impl WriterReaderPhaser {
+    /// Create a new `WriterReaderPhaser`.
+    pub fn new() -> Result<Self, HistogramErr> {
+        let p = unsafe { ffi::hdr_rust_phaser_alloc() };
+        if p.is_null() {
+            return Err(HistogramErr::AllocFail);
+        }
+
+        let res = unsafe { ffi::hdr_writer_reader_phaser_init(p) };
+        if res != 0 {
+            unsafe { ffi::hdr_rust_phaser_free(p) };
+
+            return Err(HistogramErr::init(res));
+        }
+
+        Ok(WriterReaderPhaser(p))
+    }
+
+    /// Enter a writer critical section, which lasts until the returned guard is dropped. This is
+    /// wait-free.
+    #[inline]
+    pub fn writer_enter(&self) -> PhaserWriterGuard<'_> {
+        let critical_value = unsafe { ffi::hdr_phaser_writer_enter(self.0) };
+
+        PhaserWriterGuard {
+            phaser: self,
+            critical_value,
+        }
+    }
+
+    /// Take the reader lock, which is held until the returned guard is dropped. Only one reader
+    /// can hold the lock at a time.
+    pub fn reader_lock(&self) -> PhaserReaderGuard<'_> {
+        unsafe { ffi::hdr_phaser_reader_lock(self.0) };
+
+        PhaserReaderGuard {
+            phaser: self,
+            not_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for WriterReaderPhaser {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::hdr_writer_reader_phaser_destroy(self.0);
+            ffi::hdr_rust_phaser_free(self.0);
+        }
+    }
+}
+
+/// A writer critical section of a `WriterReaderPhaser`. The section is exited when this is dropped.
+#[must_use = "the critical section is exited when the guard is dropped"]
+pub struct PhaserWriterGuard<'a> {
+    phaser: &'a WriterReaderPhaser,
+    critical_value: i64,
+}
+
+impl<'a> Drop for PhaserWriterGuard<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ffi::hdr_phaser_writer_exit(self.phaser.0, self.critical_value) }
+    }
+}
+
+/// The reader lock of a `WriterReaderPhaser`. The lock is released when this is dropped.
+#[must_use = "the reader lock is released when the guard is dropped"]
+pub struct PhaserReaderGuard<'a> {
+    phaser: &'a WriterReaderPhaser,
+    // The lock must be released by the thread that took it.
+    not_send: PhantomData<*const ()>,
+}
+
+impl<'a> PhaserReaderGuard<'a> {
+    /// Flip the phase, waiting until all writers which entered their critical section in the
+    /// previous phase have exited. `sleep_time_ns` is how long to sleep between checks; 0 yields
+    /// instead.
+    pub fn flip_phase(&mut self, sleep_time_ns: i64) {
+        unsafe { ffi::hdr_phaser_flip_phase(self.phaser.0, sleep_time_ns) }
+    }
+}
+
+impl<'a> Drop for PhaserReaderGuard<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::hdr_phaser_reader_unlock(self.phaser.0) }
+    }
+}
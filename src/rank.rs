@@ -0,0 +1,96 @@
+//! Rank queries: the counts and percentiles of values at or below a value.
+
+use crate::{ffi, Histogram, RecordedIter};
+
+impl Histogram {
+    /// Count of recorded values at or below `value`, including all values equivalent to it.
+    ///
+    /// ```
+    /// # use hdrhistogram_c::Histogram;
+    /// let mut h = Histogram::new(1, 100000, 3).unwrap();
+    /// h.extend(vec![10, 20, 30, 40]);
+    ///
+    /// assert_eq!(h.count_at_or_below(25), 2);
+    /// assert_eq!(h.count_between(20, 30), 2);
+    /// assert_eq!(h.percentile_below(30), 75.0);
+    /// ```
+    pub fn count_at_or_below(&self, value: i64) -> i64 {
+        if value < 0 {
+            return 0;
+        }
+        let lowest = unsafe { ffi::hdr_lowest_equivalent_value(self.0, value) };
+        self.count_from_lowest(|v| v <= lowest)
+    }
+
+    /// Count of recorded values between `low` and `high` inclusive, including all values
+    /// equivalent to either.
+    pub fn count_between(&self, low: i64, high: i64) -> i64 {
+        if low > high || high < 0 {
+            return 0;
+        }
+        let low = unsafe { ffi::hdr_lowest_equivalent_value(self.0, low.max(0)) };
+        self.count_at_or_below(high) - self.count_from_lowest(|v| v < low)
+    }
+
+    /// Percentage of recorded values at or below `value`, including all values equivalent to it.
+    /// This is the inverse of `value_at_percentile`. An empty histogram gives 100.
+    pub fn percentile_below(&self, value: i64) -> f64 {
+        let total_count = self.total_count();
+        if total_count == 0 {
+            return 100.0;
+        }
+        100.0 * self.count_at_or_below(value) as f64 / total_count as f64
+    }
+
+    /// Cumulative distribution of the recorded values, with a point for each non-empty bucket.
+    pub fn cdf(&self) -> CdfIter<'_> {
+        CdfIter {
+            iter: self.recorded_iter(),
+            total_count: self.total_count(),
+        }
+    }
+
+    /// Sum the counts of buckets, in increasing order, while their lowest values satisfy
+    /// `include`.
+    fn count_from_lowest(&self, include: impl Fn(i64) -> bool) -> i64 {
+        let mut count = 0;
+        for i in 0..self.get_counts_len() as i32 {
+            unsafe {
+                if !include(ffi::hdr_value_at_index(self.0, i)) {
+                    break;
+                }
+                count += ffi::hdr_count_at_index(self.0, i);
+            }
+        }
+        count
+    }
+}
+
+/// A point of a histogram's cumulative distribution, from [`cdf`](struct.Histogram.html#method.cdf).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CdfPoint {
+    /// Highest value of the bucket.
+    pub value: i64,
+    /// Count of recorded values at or below `value`.
+    pub count_at_or_below: i64,
+    /// Fraction of recorded values at or below `value`, from 0 to 1.
+    pub fraction: f64,
+}
+
+/// Iterator over the cumulative distribution of a `Histogram`.
+pub struct CdfIter<'a> {
+    iter: RecordedIter<'a>,
+    total_count: i64,
+}
+
+impl<'a> Iterator for CdfIter<'a> {
+    type Item = CdfPoint;
+
+    fn next(&mut self) -> Option<CdfPoint> {
+        self.iter.next().map(|item| CdfPoint {
+            value: item.highest_equivalent_value,
+            count_at_or_below: item.cumulative_count,
+            fraction: item.cumulative_count as f64 / self.total_count as f64,
+        })
+    }
+}
@@ -0,0 +1,172 @@
+//! Shifting, rescaling and changing the precision of recorded values.
+
+use crate::{ffi, Histogram, HistogramConfig, HistogramErr};
+
+impl Histogram {
+    /// Multiply all recorded values by 2^`binary_orders_of_magnitude`.
+    ///
+    /// Returns an error if a shifted value would be above the highest trackable value, unless the
+    /// histogram auto-resizes, in which case it grows to fit. The histogram is unchanged on error.
+    pub fn shift_values_left(
+        &mut self,
+        binary_orders_of_magnitude: u32,
+    ) -> Result<(), HistogramErr> {
+        let shift = binary_orders_of_magnitude;
+        self.shift_values(shift, |value| {
+            value
+                .checked_shl(shift)
+                .filter(|shifted| shifted >> shift == value)
+        })
+    }
+
+    /// Divide all recorded values by 2^`binary_orders_of_magnitude`.
+    ///
+    /// Returns an error if this would lose precision, that is if the shifted lowest value of a
+    /// non-empty bucket isn't the lowest value of a bucket. This happens when values near the
+    /// bottom of the range would need finer buckets than the histogram has. The histogram is
+    /// unchanged on error.
+    pub fn shift_values_right(
+        &mut self,
+        binary_orders_of_magnitude: u32,
+    ) -> Result<(), HistogramErr> {
+        let shift = binary_orders_of_magnitude;
+        let h = self.0;
+        self.shift_values(shift, |value| {
+            let shifted = value.checked_shr(shift).unwrap_or(0);
+            let lowest = unsafe { ffi::hdr_lowest_equivalent_value(h, shifted) };
+            if lowest.checked_shl(shift) == Some(value) {
+                Some(shifted)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Move every count to `shift_value(value)` of the lowest value of its bucket, or fail with
+    /// `ShiftOutOfRange` if that returns `None` or is out of range.
+    fn shift_values(
+        &mut self,
+        shift: u32,
+        shift_value: impl Fn(i64) -> Option<i64>,
+    ) -> Result<(), HistogramErr> {
+        // The copy keeps the configuration, conversion ratio and auto-resize setting.
+        let mut shifted = self.clone();
+        shifted.reset();
+
+        for i in 0..self.get_counts_len() as i32 {
+            let count = unsafe { ffi::hdr_count_at_index(self.0, i) };
+            if count == 0 {
+                continue;
+            }
+
+            let value = unsafe { ffi::hdr_value_at_index(self.0, i) };
+            match shift_value(value) {
+                Some(value) if shifted.record_values(value, count) => {}
+                _ => return Err(HistogramErr::ShiftOutOfRange(shift)),
+            }
+        }
+
+        *self = shifted;
+        Ok(())
+    }
+
+    /// Create a copy of the histogram with every value multiplied by `factor`, for converting
+    /// between units.
+    ///
+    /// The copy's lowest discernible and highest trackable values are scaled by `factor` too, with
+    /// a lowest discernible value of at least 1, so it has the same precision relative to the
+    /// values. Each count is recorded at the scaled lowest value of its bucket, rounded down.
+    ///
+    /// ```
+    /// # use hdrhistogram_c::Histogram;
+    /// let mut micros = Histogram::new(1, 3600 * 1000 * 1000, 3).unwrap();
+    /// micros.record_value(1500);
+    ///
+    /// let nanos = micros.rescale(1000.0).unwrap();
+    /// assert_eq!(nanos.lowest_discernible_value(), 1000);
+    /// assert!(nanos.values_are_equivalent(nanos.min(), 1500000));
+    /// ```
+    pub fn rescale(&self, factor: f64) -> Result<Histogram, HistogramErr> {
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(HistogramErr::InvalidScaleFactor(factor));
+        }
+
+        // Float to integer conversions saturate
+        let scale = |value: i64| (value as f64 * factor) as i64;
+
+        let mut scaled = HistogramConfig::new()
+            .lowest_discernible_value(scale(self.lowest_discernible_value()).max(1))
+            .highest_trackable_value(scale(self.highest_trackable_value()))
+            .significant_figures(self.significant_figures())
+            .auto_resize(self.is_auto_resize())
+            .build()?;
+
+        for i in 0..self.get_counts_len() as i32 {
+            let count = unsafe { ffi::hdr_count_at_index(self.0, i) };
+            if count == 0 {
+                continue;
+            }
+
+            let value = scale(unsafe { ffi::hdr_value_at_index(self.0, i) });
+            scaled.try_record_values(value, count)?;
+        }
+
+        Ok(scaled)
+    }
+
+    /// Create a copy of the histogram with `significant_figures` of precision, over the same
+    /// range of values.
+    ///
+    /// Counts are re-bucketed as by `merge_rebucketed`. With fewer significant figures, each
+    /// bucket of this histogram lies inside a bucket of the copy, so no count moves to a lower
+    /// bucket, and the copy's maximum is at least this histogram's.
+    ///
+    /// ```
+    /// # use hdrhistogram_c::Histogram;
+    /// let mut h = Histogram::new(1, 3600 * 1000 * 1000, 3).unwrap();
+    /// for v in 1..=50000 {
+    ///     h.record_value(v * v);
+    /// }
+    ///
+    /// let archived = h.with_precision(1).unwrap();
+    /// assert_eq!(archived.total_count(), h.total_count());
+    /// assert!(archived.max() >= h.max());
+    /// assert!(archived.get_memory_size() < h.get_memory_size());
+    /// # #[cfg(feature = "hdr_log")]
+    /// assert!(archived.encoded_len().unwrap() < h.encoded_len().unwrap());
+    /// ```
+    pub fn with_precision(&self, significant_figures: i32) -> Result<Histogram, HistogramErr> {
+        let mut h = self
+            .config()
+            .significant_figures(significant_figures)
+            .build()?;
+        unsafe {
+            ffi::hdr_rust_set_conversion_ratio(h.0, ffi::hdr_rust_conversion_ratio(self.0));
+        }
+
+        h.merge_rebucketed(self);
+        Ok(h)
+    }
+
+    /// Reduce the histogram to `significant_figures` of precision, as `with_precision`. Returns
+    /// an error if that's more than it has.
+    pub fn reduce_precision(&mut self, significant_figures: i32) -> Result<(), HistogramErr> {
+        let current = self.significant_figures();
+        if significant_figures > current {
+            return Err(HistogramErr::CannotIncreasePrecision {
+                current,
+                requested: significant_figures,
+            });
+        }
+
+        *self = self.with_precision(significant_figures)?;
+        Ok(())
+    }
+
+    /// Size in bytes of the binary V2 compressed encoding of the histogram, as produced by
+    /// `encode_to_vec`. The Base64 encoding from `encode` is 4 bytes for every 3 of these,
+    /// rounded up.
+    pub fn encoded_len(&self) -> Result<usize, HistogramErr> {
+        self.encode_to_vec().map(|v| v.len())
+    }
+}
@@ -0,0 +1,160 @@
+//! Serde support, enabled with the `serde` feature.
+//!
+//! `Histogram` serializes as its V2 compressed encoding: raw bytes, as from `encode_to_vec`, for
+//! binary formats, and Base64, as from `encode`, for human-readable ones. Either form is accepted
+//! when deserializing. Like decoding, this doesn't preserve whether the histogram auto-resizes.
+//!
+//! The [`expanded`](expanded/index.html) module provides a larger but readable alternative.
+
+use std::fmt;
+
+use serde::{
+    de::{self, Deserializer, SeqAccess, Visitor},
+    ser::{self, Serializer},
+    Deserialize, Serialize,
+};
+
+use crate::Histogram;
+
+impl Serialize for Histogram {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let encoded = self.encode().map_err(ser::Error::custom)?;
+            serializer.serialize_str(&encoded)
+        } else {
+            let encoded = self.encode_to_vec().map_err(ser::Error::custom)?;
+            serializer.serialize_bytes(&encoded)
+        }
+    }
+}
+
+struct EncodedVisitor;
+
+impl<'de> Visitor<'de> for EncodedVisitor {
+    type Value = Histogram;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a V2 encoded histogram")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Histogram, E> {
+        Histogram::decode(&v.to_string()).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Histogram, E> {
+        Histogram::decode_from_slice(v).map_err(E::custom)
+    }
+
+    // Some formats represent bytes as a sequence.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Histogram, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+        self.visit_bytes(&bytes)
+    }
+}
+
+impl<'de> Deserialize<'de> for Histogram {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Histogram, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(EncodedVisitor)
+        } else {
+            deserializer.deserialize_bytes(EncodedVisitor)
+        }
+    }
+}
+
+/// Expanded representation of a `Histogram`, for use with `#[serde(with = "...")]`.
+///
+/// The histogram is represented as its [`HistogramConfig`](../../struct.HistogramConfig.html)
+/// and a list of `[value, count]` pairs for each non-empty bucket, with the value being the
+/// lowest in the bucket, plus its conversion ratio if that isn't 1. This is much larger than the
+/// default encoding, but can be read and edited by hand, and it keeps the auto-resize setting.
+///
+/// ```
+/// # use hdrhistogram_c::Histogram;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(Serialize, Deserialize)]
+/// struct Report {
+///     #[serde(with = "hdrhistogram_c::serialization::expanded")]
+///     latency: Histogram,
+/// }
+///
+/// let mut latency = Histogram::new(1, 1000, 2).unwrap();
+/// latency.record_values(10, 3);
+///
+/// let json = serde_json::to_string(&Report { latency }).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"latency":{"config":{"lowest_discernible_value":1,"highest_trackable_value":1000,"#
+///         .to_string()
+///         + r#""significant_figures":2,"auto_resize":false},"counts":[[10,3]]}}"#
+/// );
+///
+/// let report: Report = serde_json::from_str(&json).unwrap();
+/// assert_eq!(report.latency.count_at_value(10), 3);
+/// ```
+pub mod expanded {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::{ffi, Histogram, HistogramConfig};
+
+    #[derive(Serialize, Deserialize)]
+    struct Expanded {
+        config: HistogramConfig,
+        // Only present when it's been set, as by `DurationHistogram`.
+        #[serde(default = "unit_ratio", skip_serializing_if = "is_unit_ratio")]
+        conversion_ratio: f64,
+        counts: Vec<(i64, i64)>,
+    }
+
+    fn unit_ratio() -> f64 {
+        1.0
+    }
+
+    fn is_unit_ratio(ratio: &f64) -> bool {
+        *ratio == 1.0
+    }
+
+    /// Serialize `h` in the expanded representation.
+    pub fn serialize<S: Serializer>(h: &Histogram, serializer: S) -> Result<S::Ok, S::Error> {
+        let counts = (0..h.get_counts_len() as i32)
+            .filter_map(|i| {
+                let count = unsafe { ffi::hdr_count_at_index(h.0, i) };
+                if count == 0 {
+                    None
+                } else {
+                    Some((unsafe { ffi::hdr_value_at_index(h.0, i) }, count))
+                }
+            })
+            .collect();
+
+        Expanded {
+            config: h.config(),
+            conversion_ratio: unsafe { ffi::hdr_rust_conversion_ratio(h.0) },
+            counts,
+        }
+        .serialize(serializer)
+    }
+
+    /// Deserialize a histogram from the expanded representation.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Histogram, D::Error> {
+        let expanded = Expanded::deserialize(deserializer)?;
+
+        let mut h = expanded.config.build().map_err(de::Error::custom)?;
+        for (value, count) in expanded.counts {
+            if count < 0 {
+                return Err(de::Error::invalid_value(
+                    de::Unexpected::Signed(count),
+                    &"a non-negative count",
+                ));
+            }
+            h.try_record_values(value, count)
+                .map_err(de::Error::custom)?;
+        }
+        unsafe { ffi::hdr_rust_set_conversion_ratio(h.0, expanded.conversion_ratio) };
+
+        Ok(h)
+    }
+}
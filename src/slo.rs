@@ -0,0 +1,242 @@
+//! Latency SLO evaluation: compliance, error budgets and burn rate alerts.
+//!
+//! An SLO such as "99% of requests under 250ms" is a [`LatencySlo`](struct.LatencySlo.html) with
+//! a threshold of 250ms, in the unit the values are recorded in, and a target of 99 percent.
+//! Recorded values at or below the threshold, including those equivalent to it, are good events
+//! and the rest are bad. The error budget is the fraction of bad events the target allows,
+//! `1 - target / 100`, and the burn rate is the fraction of events which were bad divided by the
+//! error budget, so a burn rate of 1 uses up exactly the whole budget over the SLO's period.
+//!
+//! ```
+//! # use hdrhistogram_c::{slo::{BurnWindow, LatencySlo}, Histogram};
+//! // 99% of requests under 250ms, recorded in microseconds
+//! let slo = LatencySlo::new(250000, 99.0).unwrap();
+//!
+//! // An hour of one minute interval histograms, with a slow last 5 minutes
+//! let intervals: Vec<_> = (0..60)
+//!     .map(|minute| {
+//!         let mut h = Histogram::new(1, 60 * 1000 * 1000, 3).unwrap();
+//!         h.record_values(20000, 990);
+//!         h.record_values(300000, 10);
+//!         if minute >= 55 {
+//!             h.record_values(2000000, 200);
+//!         }
+//!         h
+//!     })
+//!     .collect();
+//!
+//! let budget = slo.error_budget(&intervals);
+//! assert!(budget.burn_rate > 1.0);
+//!
+//! // Fast burn over the last 5 minutes, but not yet the whole hour
+//! let windows = [BurnWindow::new(5, 1, 14.4), BurnWindow::new(60, 5, 14.4)];
+//! let alerts = slo.burn_alerts(&intervals, &windows);
+//! assert!(alerts[0].firing);
+//! assert!(!alerts[1].firing);
+//! ```
+
+use crate::{Histogram, HistogramErr};
+
+/// A latency SLO: `target` percent of values must be at or below `threshold`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatencySlo {
+    threshold: i64,
+    target: f64,
+}
+
+/// Compliance of one histogram with an SLO.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Compliance {
+    /// Count of all recorded values.
+    pub total_count: i64,
+    /// Count of values at or below the threshold.
+    pub good_count: i64,
+    /// Percentage of values at or below the threshold. 100 if there are none.
+    pub percentage: f64,
+    /// Whether `percentage` meets the target.
+    pub met: bool,
+}
+
+/// Use of the error budget over a sequence of interval histograms.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ErrorBudget {
+    /// Count of all recorded values.
+    pub total_count: i64,
+    /// Count of values above the threshold.
+    pub bad_count: i64,
+    /// Count of values above the threshold the target allows.
+    pub allowed_bad_count: f64,
+    /// Fraction of the budget left: 1 if it's untouched, 0 if it's used up, and negative if it's
+    /// overspent.
+    pub remaining: f64,
+    /// Burn rate over all the intervals.
+    pub burn_rate: f64,
+}
+
+/// A multi-window burn rate alert condition, which fires when the burn rate over both the last
+/// `long` and the last `short` intervals exceeds `burn_rate`. The long window makes the alert
+/// significant, and the short one makes it stop soon after the problem does.
+///
+/// For example with 5 minute intervals, `BurnWindow::new(12, 1, 14.4)` checks the last hour and
+/// the last 5 minutes for a burn rate which would use 2% of a 30 day budget in an hour.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BurnWindow {
+    /// Number of intervals in the long window.
+    pub long: usize,
+    /// Number of intervals in the short window.
+    pub short: usize,
+    /// Burn rate at which the alert fires.
+    pub burn_rate: f64,
+}
+
+/// Evaluation of a [`BurnWindow`](struct.BurnWindow.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BurnAlert {
+    /// The alert condition evaluated.
+    pub window: BurnWindow,
+    /// Burn rate over the long window.
+    pub long_burn_rate: f64,
+    /// Burn rate over the short window.
+    pub short_burn_rate: f64,
+    /// Whether both burn rates exceed the window's.
+    pub firing: bool,
+}
+
+impl BurnWindow {
+    pub fn new(long: usize, short: usize, burn_rate: f64) -> Self {
+        BurnWindow {
+            long,
+            short,
+            burn_rate,
+        }
+    }
+}
+
+impl Compliance {
+    /// Count of values above the threshold.
+    pub fn bad_count(&self) -> i64 {
+        self.total_count - self.good_count
+    }
+}
+
+impl LatencySlo {
+    /// Create an SLO requiring `target` percent of values to be at or below `threshold`. The
+    /// target must be strictly between 0 and 100.
+    pub fn new(threshold: i64, target: f64) -> Result<Self, HistogramErr> {
+        if !(target > 0.0 && target < 100.0) {
+            return Err(HistogramErr::InvalidSloTarget(target));
+        }
+
+        Ok(LatencySlo { threshold, target })
+    }
+
+    pub fn threshold(&self) -> i64 {
+        self.threshold
+    }
+
+    pub fn target(&self) -> f64 {
+        self.target
+    }
+
+    /// Fraction of values the target allows to be above the threshold.
+    pub fn error_budget_fraction(&self) -> f64 {
+        1.0 - self.target / 100.0
+    }
+
+    /// Evaluate whether the values in `h` meet the SLO.
+    pub fn compliance(&self, h: &Histogram) -> Compliance {
+        let total_count = h.total_count();
+        let good_count = h.count_at_or_below(self.threshold);
+        let percentage = h.percentile_below(self.threshold);
+
+        Compliance {
+            total_count,
+            good_count,
+            percentage,
+            met: percentage >= self.target,
+        }
+    }
+
+    /// Burn rate of the values in `h`. 0 if there are none.
+    pub fn burn_rate(&self, h: &Histogram) -> f64 {
+        let c = self.compliance(h);
+        self.burn_rate_of(c.total_count, c.bad_count())
+    }
+
+    /// Error budget used by the values in `intervals`, which together make up the SLO's period.
+    pub fn error_budget<'a>(
+        &self,
+        intervals: impl IntoIterator<Item = &'a Histogram>,
+    ) -> ErrorBudget {
+        let (total_count, bad_count) = self
+            .counts(intervals)
+            .into_iter()
+            .fold((0, 0), |(total, bad), (t, b)| (total + t, bad + b));
+        let allowed_bad_count = total_count as f64 * self.error_budget_fraction();
+
+        ErrorBudget {
+            total_count,
+            bad_count,
+            allowed_bad_count,
+            remaining: if total_count == 0 {
+                1.0
+            } else {
+                1.0 - bad_count as f64 / allowed_bad_count
+            },
+            burn_rate: self.burn_rate_of(total_count, bad_count),
+        }
+    }
+
+    /// Evaluate multi-window burn rate alerts over `intervals`, which must be in chronological
+    /// order, so the windows end at the last one. Windows longer than the sequence cover all of
+    /// it.
+    pub fn burn_alerts<'a>(
+        &self,
+        intervals: impl IntoIterator<Item = &'a Histogram>,
+        windows: &[BurnWindow],
+    ) -> Vec<BurnAlert> {
+        let counts = self.counts(intervals);
+        let burn_rate_over = |len: usize| {
+            let (total, bad) = counts
+                .iter()
+                .rev()
+                .take(len)
+                .fold((0, 0), |(total, bad), &(t, b)| (total + t, bad + b));
+            self.burn_rate_of(total, bad)
+        };
+
+        windows
+            .iter()
+            .map(|&window| {
+                let long_burn_rate = burn_rate_over(window.long);
+                let short_burn_rate = burn_rate_over(window.short);
+
+                BurnAlert {
+                    window,
+                    long_burn_rate,
+                    short_burn_rate,
+                    firing: long_burn_rate > window.burn_rate && short_burn_rate > window.burn_rate,
+                }
+            })
+            .collect()
+    }
+
+    /// Total and bad counts of each interval.
+    fn counts<'a>(&self, intervals: impl IntoIterator<Item = &'a Histogram>) -> Vec<(i64, i64)> {
+        intervals
+            .into_iter()
+            .map(|h| {
+                let c = self.compliance(h);
+                (c.total_count, c.bad_count())
+            })
+            .collect()
+    }
+
+    fn burn_rate_of(&self, total_count: i64, bad_count: i64) -> f64 {
+        if total_count == 0 {
+            0.0
+        } else {
+            bad_count as f64 / total_count as f64 / self.error_budget_fraction()
+        }
+    }
+}
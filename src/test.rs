@@ -35,10 +35,10 @@ fn load_histograms() -> Loaded {
     scaled_cor.record_corrected_value(100000000 * scale, scaled_interval);
 
     Loaded {
-        raw: raw,
-        cor: cor,
-        scaled_raw: scaled_raw,
-        scaled_cor: scaled_cor,
+        raw,
+        cor,
+        scaled_raw,
+        scaled_cor,
     }
 }
 
@@ -71,6 +71,112 @@ fn test_invalid_sigfig() {
     assert!(Histogram::new(1, 3600000000, 6).is_err());
 }
 
+#[test]
+fn test_config() {
+    let config = HistogramConfig::new()
+        .lowest_discernible_value(1)
+        .highest_trackable_value(3600000000)
+        .significant_figures(3);
+
+    let buckets = config.bucket_config().unwrap();
+    assert_eq!(buckets.unit_magnitude, 0);
+    assert_eq!(buckets.sub_bucket_count, 2048);
+    assert_eq!(buckets.bucket_count, 22);
+    assert_eq!(buckets.counts_len, 23552);
+
+    let h = config.build().unwrap();
+    assert_eq!(h.lowest_discernible_value(), 1);
+    assert_eq!(h.highest_trackable_value(), 3600000000);
+    assert_eq!(h.significant_figures(), 3);
+    assert_eq!(h.unit_magnitude(), buckets.unit_magnitude);
+    assert_eq!(h.sub_bucket_count(), buckets.sub_bucket_count);
+    assert_eq!(h.bucket_count(), buckets.bucket_count);
+    assert_eq!(h.get_counts_len(), buckets.counts_len as i64);
+    assert_eq!(h.get_memory_size(), config.memory_size().unwrap());
+    assert_eq!(h.config(), config);
+
+    let h = Histogram::new(1000, 1000000, 2).unwrap();
+    assert_eq!(h.unit_magnitude(), 9);
+    assert_eq!(
+        h.config().bucket_config().unwrap().counts_len as i64,
+        h.get_counts_len()
+    );
+
+    // Getters return what was set, and what a histogram was built with
+    let config = HistogramConfig::new()
+        .lowest_discernible_value(1000)
+        .highest_trackable_value(1000000)
+        .significant_figures(2)
+        .auto_resize(false);
+    assert_eq!(config.get_lowest_discernible_value(), 1000);
+    assert_eq!(config.get_highest_trackable_value(), 1000000);
+    assert_eq!(config.get_significant_figures(), 2);
+    assert!(!config.is_auto_resize());
+    let h = config.build().unwrap();
+    assert_eq!(h.config(), config);
+    assert_eq!(
+        h.config().get_lowest_discernible_value(),
+        h.lowest_discernible_value()
+    );
+    assert_eq!(
+        h.config().get_highest_trackable_value(),
+        h.highest_trackable_value()
+    );
+    assert_eq!(
+        h.config().get_significant_figures(),
+        h.significant_figures()
+    );
+    assert_eq!(h.config().is_auto_resize(), h.is_auto_resize());
+
+    // The default starts small and grows
+    let mut h = HistogramConfig::default().build().unwrap();
+    assert!(h.get_memory_size() <= 32 * 1024);
+    assert!(h.record_value(3600000000));
+    assert_eq!(h.config().get_significant_figures(), 3);
+
+    assert!(matches!(
+        HistogramConfig::new().significant_figures(6).memory_size(),
+        Err(HistogramErr::InvalidSignificantFigures(6))
+    ));
+    assert!(matches!(
+        HistogramConfig::new()
+            .lowest_discernible_value(100)
+            .highest_trackable_value(150)
+            .build(),
+        Err(HistogramErr::InvalidHighestTrackableValue {
+            lowest: 100,
+            highest: 150
+        })
+    ));
+}
+
+#[test]
+fn test_init_errors() {
+    assert!(matches!(
+        Histogram::new(0, 6481024, 2),
+        Err(HistogramErr::InvalidLowestDiscernibleValue(0))
+    ));
+    assert!(matches!(
+        Histogram::new(80, 110, 5),
+        Err(HistogramErr::InvalidHighestTrackableValue {
+            lowest: 80,
+            highest: 110
+        })
+    ));
+    assert!(matches!(
+        Histogram::new(1, 3600000000, 0),
+        Err(HistogramErr::InvalidSignificantFigures(0))
+    ));
+    assert!(matches!(
+        Histogram::new(1, 3600000000, 6),
+        Err(HistogramErr::InvalidSignificantFigures(6))
+    ));
+    assert!(matches!(
+        IntervalRecorder::new(1, 1000, 6),
+        Err(HistogramErr::InvalidSignificantFigures(6))
+    ));
+}
+
 #[test]
 fn test_total_count() {
     let Loaded { raw, cor, .. } = load_histograms();
@@ -95,6 +201,25 @@ fn test_get_min_value() {
     assert_eq!(cor.min(), 1000);
 }
 
+#[test]
+fn test_value_at_percentiles() {
+    let Loaded { raw, cor, .. } = load_histograms();
+    let percentiles = [30.0, 99.0, 99.99, 99.999, 100.0];
+
+    for h in &[raw, cor] {
+        let expected: Vec<_> = percentiles
+            .iter()
+            .map(|&p| h.value_at_percentile(p))
+            .collect();
+
+        assert_eq!(&h.value_at_percentiles(&percentiles)[..], &expected[..]);
+
+        let mut values = [0; 5];
+        h.value_at_percentiles_into(&percentiles, &mut values);
+        assert_eq!(&values[..], &expected[..]);
+    }
+}
+
 #[test]
 fn test_percentiles() {
     let Loaded { raw, cor, .. } = load_histograms();
@@ -162,7 +287,172 @@ fn test_percentiles() {
     ));
 }
 
-/*
+#[test]
+fn test_write_percentiles() {
+    let Loaded { raw, .. } = load_histograms();
+
+    let mut out = Vec::new();
+    raw.write_percentiles(&mut out, 5, 1000.0, PercentilesFormat::Classic)
+        .unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<_> = out.lines().collect();
+
+    assert_eq!(
+        lines[0],
+        "       Value   Percentile   TotalCount 1/(1-Percentile)"
+    );
+    assert_eq!(lines[1], "");
+    assert_eq!(
+        lines[2],
+        "       1.000     0.000000        10000         1.00"
+    );
+    assert_eq!(
+        lines[lines.len() - 4],
+        "  100007.935     1.000000        10001          inf"
+    );
+    assert_eq!(
+        &lines[lines.len() - 3..],
+        &[
+            "#[Mean    =       10.996, StdDeviation   =      999.642]",
+            "#[Max     =   100007.935, Total count    =        10001]",
+            "#[Buckets =           22, SubBuckets     =         2048]",
+        ]
+    );
+
+    let mut csv = Vec::new();
+    raw.write_percentiles(&mut csv, 5, 1000.0, PercentilesFormat::Csv)
+        .unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().count(), lines.len() - 4);
+    assert!(csv.ends_with("\n100007.935,1.000000,10001,inf\n"));
+}
+
+#[test]
+fn test_rank() {
+    let Loaded { raw, cor, .. } = load_histograms();
+
+    assert_eq!(raw.count_at_or_below(999), 0);
+    assert_eq!(raw.count_at_or_below(1000), 10000);
+    assert_eq!(raw.count_at_or_below(99000000), 10000);
+    assert_eq!(raw.count_at_or_below(100000000), 10001);
+    assert_eq!(raw.count_at_or_below(i64::MAX), 10001);
+    assert_eq!(raw.count_at_or_below(-1), 0);
+
+    assert_eq!(raw.count_between(0, i64::MAX), 10001);
+    assert_eq!(raw.count_between(1000, 1000), 10000);
+    assert_eq!(raw.count_between(1001, 100000000), 1);
+    assert_eq!(raw.count_between(100000000, 1000), 0);
+
+    assert_eq!(raw.percentile_below(1000), 100.0 * 10000.0 / 10001.0);
+    assert_eq!(raw.percentile_below(100000000), 100.0);
+    assert_eq!(
+        Histogram::new(1, 1000, 3).unwrap().percentile_below(10),
+        100.0
+    );
+
+    // The inverse of value_at_percentile
+    for &p in &[10.0, 50.0, 75.0, 90.0, 99.0, 99.9] {
+        let v = cor.value_at_percentile(p);
+        assert!(cor.percentile_below(v) >= p);
+        assert!(cor.percentile_below(cor.lowest_equivalent_value(v) - 1) < p);
+    }
+
+    let cdf: Vec<_> = raw.cdf().collect();
+    assert_eq!(cdf.len(), 2);
+    assert_eq!(
+        cdf[0],
+        CdfPoint {
+            value: 1000,
+            count_at_or_below: 10000,
+            fraction: 10000.0 / 10001.0
+        }
+    );
+    assert!(raw.values_are_equivalent(cdf[1].value, 100000000));
+    assert_eq!(cdf[1].fraction, 1.0);
+
+    let cdf: Vec<_> = cor.cdf().collect();
+    assert!(cdf
+        .windows(2)
+        .all(|w| w[0].value < w[1].value && w[0].fraction < w[1].fraction));
+    assert!(cdf
+        .iter()
+        .step_by(50)
+        .all(|p| p.count_at_or_below == cor.count_at_or_below(p.value)));
+}
+
+#[test]
+#[cfg(feature = "hdr_log")]
+fn test_slo() {
+    use crate::slo::{BurnWindow, LatencySlo};
+    use std::time::{Duration, SystemTime};
+
+    let Loaded { raw, .. } = load_histograms();
+
+    assert!(matches!(
+        LatencySlo::new(1000, 100.0),
+        Err(HistogramErr::InvalidSloTarget(_))
+    ));
+    assert!(LatencySlo::new(1000, 0.0).is_err());
+    assert!(LatencySlo::new(1000, f64::NAN).is_err());
+
+    // 10000 of 10001 values are 1000, one is 100000000
+    let slo = LatencySlo::new(1000, 99.99).unwrap();
+    let c = slo.compliance(&raw);
+    assert_eq!(c.total_count, 10001);
+    assert_eq!(c.good_count, 10000);
+    assert_eq!(c.bad_count(), 1);
+    assert!(c.met);
+    assert!(!LatencySlo::new(1000, 99.999).unwrap().compliance(&raw).met);
+    assert!(!LatencySlo::new(999, 50.0).unwrap().compliance(&raw).met);
+
+    let empty = Histogram::new(1, 1000, 3).unwrap();
+    assert!(slo.compliance(&empty).met);
+    assert_eq!(slo.burn_rate(&empty), 0.0);
+
+    // Intervals read back from a log, with one bad value in each of the last two
+    let start = SystemTime::UNIX_EPOCH;
+    let mut log = LogWriter::new(Vec::new());
+    log.write_header(start).unwrap();
+    for i in 0..10 {
+        let mut h = Histogram::new(1, 3600 * 1000 * 1000, 3).unwrap();
+        h.record_values(100, 999);
+        h.record_value(if i < 8 { 500 } else { 5000 });
+        log.write_histogram(start, Duration::from_secs(60), &h, None)
+            .unwrap();
+    }
+    let log = log.into_inner();
+    let entries: Vec<_> = LogReader::new(&log[..]).collect::<Result<_, _>>().unwrap();
+    let intervals: Vec<_> = entries.iter().map(|e| &e.histogram).collect();
+
+    let slo = LatencySlo::new(1000, 99.9).unwrap();
+    let budget = slo.error_budget(intervals.iter().cloned());
+    assert_eq!(budget.total_count, 10000);
+    assert_eq!(budget.bad_count, 2);
+    assert!((budget.allowed_bad_count - 10.0).abs() < 1e-6);
+    assert!((budget.remaining - 0.8).abs() < 1e-6);
+    assert!((budget.burn_rate - 0.2).abs() < 1e-6);
+
+    let windows = [
+        BurnWindow::new(2, 1, 0.5),
+        BurnWindow::new(10, 1, 0.5),
+        BurnWindow::new(100, 100, 0.1),
+    ];
+    let alerts = slo.burn_alerts(intervals.iter().cloned(), &windows);
+    assert_eq!(alerts.len(), 3);
+    assert!((alerts[0].long_burn_rate - 1.0).abs() < 1e-6);
+    assert!((alerts[0].short_burn_rate - 1.0).abs() < 1e-6);
+    assert!(alerts[0].firing);
+    assert!((alerts[1].long_burn_rate - 0.2).abs() < 1e-6);
+    assert!(!alerts[1].firing);
+    assert_eq!(alerts[2].window, windows[2]);
+    assert!(alerts[2].firing);
+
+    assert!(slo
+        .burn_alerts(Vec::new(), &windows)
+        .iter()
+        .all(|a| !a.firing && a.long_burn_rate == 0.0));
+}
+
 #[test]
 fn test_recorded_values() {
     let Loaded { raw, cor, .. } = load_histograms();
@@ -193,8 +483,7 @@ fn test_recorded_values() {
     }
     assert_eq!(total_added_count, 20000);
 }
-*/
-/*
+
 #[test]
 fn test_linear_values() {
     let Loaded { raw, cor, .. } = load_histograms();
@@ -230,8 +519,7 @@ fn test_linear_values() {
     assert_eq!(last + 1, 10000);
     assert_eq!(total_added_count, 20000);
 }
-*/
-/*
+
 #[test]
 fn test_logarithmic_values() {
     let Loaded { raw, cor, .. } = load_histograms();
@@ -267,7 +555,53 @@ fn test_logarithmic_values() {
     assert_eq!(last, 14);
     assert_eq!(total_added_count, 20000);
 }
-*/
+
+#[test]
+#[should_panic(expected = "value_units_per_bucket must be positive")]
+fn test_linear_iter_zero_bucket() {
+    let h = Histogram::new(1, 1000, 3).unwrap();
+    h.linear_iter(0);
+}
+
+#[test]
+#[should_panic(expected = "value_units_first_bucket must be positive")]
+fn test_log_iter_zero_bucket() {
+    let h = Histogram::new(1, 1000, 3).unwrap();
+    h.log_iter(-1, 2.0);
+}
+
+#[test]
+#[should_panic(expected = "log_base must be at least 2")]
+fn test_log_iter_base_one() {
+    let h = Histogram::new(1, 1000, 3).unwrap();
+    h.log_iter(10, 1.0);
+}
+
+#[test]
+#[should_panic(expected = "log_base must be at least 2")]
+fn test_log_iter_fractional_base() {
+    let h = Histogram::new(1, 1000, 3).unwrap();
+    h.log_iter(10, 1.5);
+}
+
+#[test]
+fn test_percentile_values() {
+    let Loaded { raw, .. } = load_histograms();
+
+    let mut last_percentile = 0.0;
+    let mut last = None;
+    for item in raw.percentile_iter(5) {
+        assert!(item.percentile >= last_percentile);
+        last_percentile = item.percentile;
+        last = Some(item);
+    }
+
+    let last = last.unwrap();
+    assert_eq!(last.percentile, 100.0);
+    assert_eq!(last.cumulative_count, raw.total_count());
+    assert!(raw.values_are_equivalent(last.value, 100000000));
+}
+
 #[test]
 fn test_reset() {
     let Loaded {
@@ -316,6 +650,328 @@ fn test_out_of_range_values() {
     assert!(!h.record_value(32768));
 }
 
+#[test]
+fn test_auto_resize() {
+    let mut h = HistogramConfig::new()
+        .highest_trackable_value(1000)
+        .significant_figures(4)
+        .auto_resize(true)
+        .build()
+        .unwrap();
+    assert!(h.is_auto_resize());
+
+    for v in 1..=1000 {
+        assert!(h.record_value(v));
+    }
+    let counts_len = h.get_counts_len();
+    let memory_size = h.get_memory_size();
+
+    assert!(h.record_value(32768));
+    assert!(h.record_values(1000000, 3));
+    assert!(h.try_record_corrected_value(5000000, 1000000).is_ok());
+    assert!(!h.record_value(-1));
+
+    assert!(h.highest_trackable_value() >= 5000000);
+    assert!(h.get_counts_len() > counts_len);
+    assert!(h.get_memory_size() > memory_size);
+    assert_eq!(h.significant_figures(), 4);
+    assert_eq!(h.lowest_discernible_value(), 1);
+    assert_eq!(h.total_count(), 1000 + 1 + 3 + 5);
+    assert_eq!(h.min(), 1);
+    assert!(h.values_are_equivalent(h.max(), 5000000));
+    for v in 1..=1000 {
+        assert_eq!(h.count_at_value(v), 1);
+    }
+    assert_eq!(h.count_at_value(32768), 1);
+    assert_eq!(h.count_at_value(1000000), 4);
+    assert_eq!(h.count_at_value(4000000), 1);
+
+    let mut clone = h.clone();
+    assert!(clone.is_auto_resize());
+    assert!(clone.record_value(i64::MAX / 4));
+
+    let mut dest = HistogramConfig::new()
+        .highest_trackable_value(1000)
+        .significant_figures(4)
+        .auto_resize(true)
+        .build()
+        .unwrap();
+    assert_eq!(dest.add(&clone), 0);
+    assert_eq!(dest.total_count(), clone.total_count());
+    assert_eq!(dest.count_at_value(i64::MAX / 4), 1);
+
+    let mut fixed = Histogram::new(1, 1000, 4).unwrap();
+    assert!(!fixed.is_auto_resize());
+    assert_eq!(fixed.add(&h), 9);
+}
+
+#[test]
+#[cfg(feature = "hdr_log")]
+fn test_duration_histogram() {
+    use std::time::Duration;
+
+    assert_eq!(
+        TimeUnit::Microseconds.from_duration(Duration::from_nanos(1999)),
+        1
+    );
+    assert_eq!(TimeUnit::Nanoseconds.from_duration(Duration::MAX), i64::MAX);
+    assert_eq!(
+        TimeUnit::Milliseconds.to_duration(-5),
+        Duration::from_millis(0)
+    );
+
+    let mut h = DurationHistogram::new(
+        TimeUnit::Microseconds,
+        Duration::from_micros(1),
+        Duration::from_secs(3600),
+        3,
+    )
+    .unwrap();
+    assert_eq!(h.histogram().highest_trackable_value(), 3600000000);
+    assert_eq!(h.min(), Duration::ZERO);
+    assert_eq!(h.max(), Duration::ZERO);
+
+    for ms in 1..=100 {
+        assert!(h.record(Duration::from_millis(ms)));
+    }
+    assert!(h.record_n(Duration::from_micros(500), 10));
+    assert!(!h.record(Duration::from_secs(7200)));
+    assert!(matches!(
+        h.try_record(Duration::MAX),
+        Err(HistogramErr::ValueOutOfRange(i64::MAX))
+    ));
+
+    assert_eq!(h.total_count(), 110);
+    assert_eq!(h.min(), Duration::from_micros(500));
+    assert_eq!(h.count_at(Duration::from_micros(500)), 10);
+    assert!(h
+        .histogram()
+        .values_are_equivalent(h.max().as_micros() as i64, 100000));
+    assert!(h
+        .histogram()
+        .values_are_equivalent(h.value_at_percentile(50.0).as_micros() as i64, 45000));
+    assert!(h.mean() > Duration::from_millis(45) && h.mean() < Duration::from_millis(47));
+
+    // The unit survives encoding
+    let decoded = DurationHistogram::decode(&h.encode().unwrap()).unwrap();
+    assert_eq!(decoded, h);
+    let decoded = DurationHistogram::decode_from_slice(&h.encode_to_vec().unwrap()).unwrap();
+    assert_eq!(decoded.unit(), TimeUnit::Microseconds);
+
+    let plain = Histogram::new(1, 1000, 3).unwrap();
+    assert!(matches!(
+        DurationHistogram::decode(&plain.encode().unwrap()),
+        Err(HistogramErr::UnknownTimeUnit(r)) if r == 1.0
+    ));
+
+    // Units can't be mixed
+    let ms = DurationHistogram::from_histogram(plain, TimeUnit::Milliseconds);
+    assert!(matches!(
+        h.add(&ms),
+        Err(HistogramErr::TimeUnitMismatch {
+            expected: TimeUnit::Microseconds,
+            found: TimeUnit::Milliseconds
+        })
+    ));
+    h.add(&decoded).unwrap();
+    assert_eq!(h.total_count(), 220);
+}
+
+#[test]
+fn test_shift_values() {
+    let Loaded { raw, .. } = load_histograms();
+
+    let mut h = raw.clone();
+    h.shift_values_left(3).unwrap();
+    assert_eq!(h.total_count(), raw.total_count());
+    assert_eq!(h.min(), 8000);
+    assert_eq!(h.count_at_value(8000), 10000);
+    assert!(h.values_are_equivalent(h.max(), 800000000));
+    assert!(h.values_are_equivalent(h.value_at_percentile(99.0), 8000));
+
+    h.shift_values_right(3).unwrap();
+    assert_eq!(h, raw);
+
+    // Out of range, and the histogram is unchanged
+    assert!(matches!(
+        h.shift_values_left(6),
+        Err(HistogramErr::ShiftOutOfRange(6))
+    ));
+    assert!(matches!(
+        h.shift_values_left(64),
+        Err(HistogramErr::ShiftOutOfRange(64))
+    ));
+    assert_eq!(h, raw);
+
+    // Unless it auto-resizes
+    h.set_auto_resize(true);
+    h.shift_values_left(6).unwrap();
+    assert!(h.values_are_equivalent(h.max(), 6400000000));
+
+    // 1000 >> 4 would need finer buckets
+    let mut h = raw.clone();
+    assert!(matches!(
+        h.shift_values_right(4),
+        Err(HistogramErr::ShiftOutOfRange(4))
+    ));
+    assert_eq!(h, raw);
+    h.shift_values_right(3).unwrap();
+    assert_eq!(h.min(), 125);
+}
+
+#[test]
+fn test_rescale() {
+    let mut micros = Histogram::new(1, 3600 * 1000 * 1000, 3).unwrap();
+    micros.record_values(1000, 5);
+    micros.record_value(2500);
+
+    let nanos = micros.rescale(1000.0).unwrap();
+    assert_eq!(nanos.lowest_discernible_value(), 1000);
+    assert_eq!(nanos.highest_trackable_value(), 3600 * 1000 * 1000 * 1000);
+    assert_eq!(nanos.significant_figures(), 3);
+    assert_eq!(nanos.total_count(), 6);
+    assert!(nanos.values_are_equivalent(nanos.min(), 1000000));
+    assert!(nanos.values_are_equivalent(nanos.max(), 2500000));
+
+    let millis = micros.rescale(0.001).unwrap();
+    assert_eq!(millis.lowest_discernible_value(), 1);
+    assert_eq!(millis.count_at_value(1), 5);
+    assert_eq!(millis.count_at_value(2), 1);
+
+    assert!(matches!(
+        micros.rescale(0.0),
+        Err(HistogramErr::InvalidScaleFactor(_))
+    ));
+    assert!(matches!(
+        micros.rescale(f64::NAN),
+        Err(HistogramErr::InvalidScaleFactor(_))
+    ));
+    assert!(matches!(
+        micros.rescale(1e-10),
+        Err(HistogramErr::InvalidHighestTrackableValue { .. })
+    ));
+}
+
+#[test]
+#[cfg(feature = "hdr_log")]
+fn test_reduce_precision() {
+    let Loaded { cor, .. } = load_histograms();
+
+    let mut h = cor.clone();
+    h.reduce_precision(2).unwrap();
+    assert_eq!(h.significant_figures(), 2);
+    assert_eq!(h.lowest_discernible_value(), cor.lowest_discernible_value());
+    assert_eq!(h.highest_trackable_value(), cor.highest_trackable_value());
+    assert_eq!(h.total_count(), cor.total_count());
+    assert!(h.get_memory_size() < cor.get_memory_size());
+
+    // Values only move up, within their coarser bucket
+    assert!(h.max() >= cor.max());
+    assert!(h.values_are_equivalent(h.max(), cor.max()));
+    assert!(h.values_are_equivalent(h.min(), cor.min()));
+    for &p in &[50.0, 90.0, 99.0, 99.99] {
+        assert!(h.values_are_equivalent(h.value_at_percentile(p), cor.value_at_percentile(p)));
+    }
+
+    // The conversion ratio is kept
+    let d = DurationHistogram::from_histogram(cor, TimeUnit::Nanoseconds);
+    let reduced = d.histogram().with_precision(1).unwrap();
+    let decoded = DurationHistogram::decode(&reduced.encode().unwrap()).unwrap();
+    assert_eq!(decoded.unit(), TimeUnit::Nanoseconds);
+
+    assert!(matches!(
+        h.reduce_precision(3),
+        Err(HistogramErr::CannotIncreasePrecision {
+            current: 2,
+            requested: 3
+        })
+    ));
+    assert!(matches!(
+        h.with_precision(0),
+        Err(HistogramErr::InvalidSignificantFigures(0))
+    ));
+}
+
+#[test]
+fn test_merge_rebucketed() {
+    let Loaded {
+        raw, scaled_raw, ..
+    } = load_histograms();
+
+    // Coarser destination
+    let mut coarse = Histogram::new(1, 3600 * 1000 * 1000, 2).unwrap();
+    let report = coarse.merge_rebucketed(&raw);
+    assert_eq!(
+        report,
+        MergeReport {
+            merged: 10001,
+            clamped: 0,
+            precision_lost: 10001,
+            dropped: 0
+        }
+    );
+    assert_eq!(coarse.total_count(), 10001);
+    assert!(coarse.values_are_equivalent(coarse.min(), 1000));
+    assert!(coarse.values_are_equivalent(coarse.max(), 100000000));
+
+    // Narrower destination
+    let mut narrow = Histogram::new(1, 1000000, 3).unwrap();
+    let report = narrow.merge_rebucketed(&raw);
+    assert_eq!((report.merged, report.clamped), (10001, 1));
+    assert_eq!(narrow.count_at_value(1000000), 1);
+
+    // Auto-resizing destinations grow instead
+    narrow.reset();
+    narrow.set_auto_resize(true);
+    assert_eq!(narrow.merge_rebucketed(&raw).clamped, 0);
+    assert_eq!(narrow.diff(&raw).unwrap(), vec![]);
+
+    // Covering configurations lose nothing
+    let config = HistogramConfig::covering(vec![&raw, &scaled_raw, &coarse]);
+    assert_eq!(
+        config,
+        HistogramConfig::new()
+            .lowest_discernible_value(1)
+            .highest_trackable_value(scaled_raw.highest_trackable_value())
+            .significant_figures(3)
+            .auto_resize(false)
+    );
+    let mut merged = config.build().unwrap();
+    for h in &[&raw, &scaled_raw, &coarse] {
+        let report = merged.merge_rebucketed(h);
+        assert_eq!(report.merged, h.total_count());
+        assert_eq!(report.clamped + report.precision_lost, 0);
+    }
+    assert_eq!(merged.total_count(), 30003);
+    assert_eq!(
+        HistogramConfig::covering(vec![]),
+        HistogramConfig::default()
+    );
+}
+
+#[test]
+fn test_try_record() {
+    let mut h = Histogram::new(1, 1000, 4).unwrap();
+
+    assert!(h.try_record_value(32767).is_ok());
+    assert!(h.try_record_values(10, 5).is_ok());
+    assert!(h.try_record_corrected_value(1000, 100).is_ok());
+    assert!(h.try_record_corrected_values(1000, 2, 100).is_ok());
+    assert!(matches!(
+        h.try_record_value(32768),
+        Err(HistogramErr::ValueOutOfRange(32768))
+    ));
+    assert!(matches!(
+        h.try_record_values(-1, 1),
+        Err(HistogramErr::ValueOutOfRange(-1))
+    ));
+    assert!(matches!(
+        h.try_record_corrected_value(40000, 100),
+        Err(HistogramErr::ValueOutOfRange(40000))
+    ));
+    assert_eq!(h.total_count(), 1 + 5 + 10 + 20);
+}
+
 #[test]
 fn test_create_with_large_values() {
     let mut h = Histogram::new(20000000, 100000000, 5).unwrap();
@@ -344,9 +1000,207 @@ fn test_clone() {
 
     assert_eq!(h.total_count(), b.total_count());
     assert_eq!(h.count_at_value(100000000), b.count_at_value(100000000));
+    assert_eq!(h, b);
+}
+
+#[test]
+fn test_compare() {
+    let Loaded { raw, cor, .. } = load_histograms();
+
+    assert_eq!(raw, raw.clone());
+    assert_ne!(raw, cor);
+    assert_eq!(raw.diff(&raw.clone()).unwrap(), vec![]);
+
+    // Correcting only added counts, at multiples of the interval
+    let diffs = raw.diff(&cor).unwrap();
+    assert!(raw.values_are_equivalent(diffs[0].value, 10000));
+    assert_eq!((diffs[0].count, diffs[0].other_count), (0, 1));
+    assert!(diffs.iter().all(|d| d.count < d.other_count));
+    assert_eq!(
+        diffs.iter().map(|d| d.other_count - d.count).sum::<i64>(),
+        cor.total_count() - raw.total_count()
+    );
+
+    // Same counts, different range
+    let mut a = Histogram::new(1, 100000, 3).unwrap();
+    let mut b = Histogram::new(1, 1000000, 3).unwrap();
+    a.record_value(10);
+    b.record_value(10);
+    assert_ne!(a, b);
+    assert_eq!(a.diff(&b).unwrap(), vec![]);
+
+    // Same counts, different units
+    let micros = DurationHistogram::from_histogram(a.clone(), TimeUnit::Microseconds);
+    let millis = DurationHistogram::from_histogram(a.clone(), TimeUnit::Milliseconds);
+    assert_ne!(micros.histogram(), &a);
+    assert_ne!(micros, millis);
+    assert_eq!(micros, micros.clone());
+
+    b.record_value(500000);
+    let diffs = a.diff(&b).unwrap();
+    assert_eq!(diffs.len(), 1);
+    assert!(b.values_are_equivalent(diffs[0].value, 500000));
+    assert_eq!((diffs[0].count, diffs[0].other_count), (0, 1));
+
+    assert!(matches!(
+        a.diff(&Histogram::new(1, 100000, 2).unwrap()),
+        Err(HistogramErr::IncompatibleHistograms)
+    ));
+
+    let debug = format!("{:?}", a);
+    assert!(debug.starts_with("Histogram { lowest_discernible_value: 1, "));
+    assert!(debug.contains("total_count: 1, min: 10, max: 10"));
+}
+
+#[test]
+fn test_subtract() {
+    let mut h = Histogram::new(1, 3600000000, 3).unwrap();
+    h.record_values(10, 5);
+    h.record_value(1000);
+    let earlier = h.clone();
+
+    h.record_values(10, 2);
+    h.record_value(500);
+    h.record_value(100000);
+
+    let mut delta = h.clone();
+    delta.subtract(&earlier).unwrap();
+    assert_eq!(delta.total_count(), 4);
+    assert_eq!(delta.count_at_value(10), 2);
+    assert_eq!(delta.count_at_value(1000), 0);
+    assert_eq!(delta.min(), 10);
+    assert!(delta.values_are_equivalent(delta.max(), 100000));
+
+    // Subtracting everything leaves an empty histogram
+    delta.subtract(&delta.clone()).unwrap();
+    assert_eq!(delta.total_count(), 0);
+    assert_eq!(delta.max(), 0);
+
+    // Failures leave the histogram unchanged
+    let mut small = earlier.clone();
+    assert!(matches!(
+        small.subtract(&h),
+        Err(HistogramErr::NegativeCount(10))
+    ));
+    assert_eq!(small.total_count(), 6);
+
+    let mut narrow = Histogram::new(1, 10000, 3).unwrap();
+    narrow.record_values(10, 7);
+    narrow.record_value(500);
+    narrow.record_value(1000);
+    assert!(matches!(
+        narrow.subtract(&h),
+        Err(HistogramErr::ValueOutOfRange(_))
+    ));
+
+    let coarse = Histogram::new(1, 3600000000, 2).unwrap();
+    assert!(matches!(
+        h.subtract(&coarse),
+        Err(HistogramErr::IncompatibleHistograms)
+    ));
+    assert_eq!(h.total_count(), 10);
+}
+
+#[test]
+fn test_subtract_shifted() {
+    let mut h = Histogram::new(1, 3600000000, 3).unwrap();
+    h.record_values(10, 5);
+    h.record_value(1000);
+    h.shift_values_left(4).unwrap();
+
+    let mut earlier = Histogram::new(1, 3600000000, 3).unwrap();
+    earlier.record_values(10 << 4, 3);
+
+    h.subtract(&earlier).unwrap();
+    assert_eq!(h.total_count(), 3);
+    assert_eq!(h.count_at_value(10 << 4), 2);
+    assert_eq!(h.count_at_value(1000 << 4), 1);
+}
+
+#[test]
+#[cfg(feature = "rust_deflate")]
+fn test_subtract_normalized() {
+    use miniz_oxide::{deflate::compress_to_vec_zlib, inflate::decompress_to_vec_zlib};
+
+    // Re-encode with a normalizing index offset, as left behind by HdrHistogram Java's shifts
+    let Loaded { cor, .. } = load_histograms();
+    let enc = cor.encode_to_vec().unwrap();
+    let mut v2 = decompress_to_vec_zlib(&enc[8..]).unwrap();
+    v2[8..12].copy_from_slice(&5i32.to_be_bytes());
+    let compressed = compress_to_vec_zlib(&v2, 6);
+    let mut shifted = enc[..4].to_vec();
+    shifted.extend_from_slice(&(compressed.len() as i32).to_be_bytes());
+    shifted.extend_from_slice(&compressed);
+
+    let mut h = Histogram::decode_from_slice(&shifted).unwrap();
+    assert_eq!(
+        Histogram::decode_from_slice(&h.encode_to_vec().unwrap()).unwrap(),
+        h
+    );
+    let counts: Vec<_> = h.recorded_iter().map(|c| (c.value, c.count)).collect();
+    let (value, count) = counts[counts.len() / 2];
+
+    let mut part = Histogram::new(1, 3600000000, 3).unwrap();
+    part.record_values(value, count - 1);
+    h.subtract(&part).unwrap();
+
+    assert_eq!(h.total_count(), cor.total_count() - (count - 1));
+    assert_eq!(h.count_at_value(value), 1);
+    for &(v, c) in counts.iter().filter(|&&(v, _)| v != value) {
+        assert_eq!(h.count_at_value(v), c);
+    }
+
+    h.subtract(&h.clone()).unwrap();
+    assert_eq!(h.total_count(), 0);
+    assert!(h.recorded_iter().next().is_none());
+}
+
+#[test]
+fn test_ops() {
+    let mut a = Histogram::new(1, 100000, 3).unwrap();
+    a.extend(vec![10, 20, 30]);
+    let mut b = Histogram::new(1, 1000000, 3).unwrap();
+    b.extend(1..=3);
+
+    let sum = a.clone() + &b;
+    assert_eq!(sum.total_count(), 6);
+    assert_eq!(sum.highest_trackable_value(), 100000);
+
+    let mut diff = sum.clone();
+    diff -= &b;
+    assert_eq!(diff.total_count(), 3);
+    assert_eq!(diff.min(), 10);
+
+    let total: Histogram = vec![a.clone(), b.clone(), diff].into_iter().collect();
+    assert_eq!(total.total_count(), 9);
+    assert_eq!(total.highest_trackable_value(), 1000000);
+    assert!(!total.is_auto_resize());
+    assert_eq!(vec![&a, &b].into_iter().sum::<Histogram>().total_count(), 6);
+
+    let empty: Histogram = Vec::<Histogram>::new().into_iter().sum();
+    assert_eq!(empty.total_count(), 0);
+    assert_eq!(empty.config(), HistogramConfig::default());
+
+    // Dropped counts are reported
+    b.record_value(500000);
+    assert!(matches!(
+        a.clone().try_add(&b),
+        Err(HistogramErr::CountsDropped(1))
+    ));
+    let shards: Histogram = vec![a.clone(), b.clone()].into_iter().sum();
+    assert_eq!(shards.total_count(), 7);
+    assert_eq!(shards.count_at_value(500000), 1);
+    assert!(std::panic::catch_unwind(move || a + &b).is_err());
+
+    let mut c = Histogram::new(1, 1000, 3).unwrap();
+    c.extend(vec![10, -1, 100000]);
+    assert_eq!(c.total_count(), 2);
+    assert_eq!(c.count_at_value(100000), 1);
+    assert!(!c.is_auto_resize());
 }
 
 #[test]
+#[cfg(feature = "hdr_log")]
 fn test_codec() {
     let Loaded { raw, .. } = load_histograms();
 
@@ -357,9 +1211,552 @@ fn test_codec() {
     let dec = Histogram::decode(&enc).unwrap();
 
     assert_eq!(raw.total_count(), dec.total_count());
+    assert_eq!(raw, dec);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_serde() {
+    use serde_test::{assert_tokens, Configure, Token};
+    use std::time::Duration;
+
+    let Loaded { raw, .. } = load_histograms();
+
+    let json = serde_json::to_string(&raw).unwrap();
+    assert_eq!(json, format!("\"{}\"", raw.encode().unwrap()));
+    assert_eq!(serde_json::from_str::<Histogram>(&json).unwrap(), raw);
+
+    let encoded: &'static [u8] = Box::leak(raw.encode_to_vec().unwrap().into_boxed_slice());
+    assert_tokens(&raw.clone().compact(), &[Token::Bytes(encoded)]);
+    assert!(serde_json::from_str::<Histogram>("\"not a histogram\"").is_err());
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Expanded(#[serde(with = "serialization::expanded")] Histogram);
+
+    let mut h = HistogramConfig::new()
+        .highest_trackable_value(100000)
+        .auto_resize(true)
+        .build()
+        .unwrap();
+    h.record_values(1000, 5);
+    h.record_value(2000000);
+
+    let json = serde_json::to_string(&Expanded(h.clone())).unwrap();
+    let Expanded(expanded) = serde_json::from_str(&json).unwrap();
+    assert_eq!(expanded, h);
+    assert!(expanded.is_auto_resize());
+
+    // Counts must be in range
+    let json = r#"{"config":{"lowest_discernible_value":1,"highest_trackable_value":1000,"#
+        .to_string()
+        + r#""significant_figures":2,"auto_resize":false},"counts":[[5000,1]]}"#;
+    let err = serde_json::from_str::<Expanded>(&json).err().unwrap();
+    assert!(err
+        .to_string()
+        .contains("outside the histogram's trackable range"));
+
+    // and not negative
+    let json = json.replace("[[5000,1]]", "[[10,-1]]");
+    let err = serde_json::from_str::<Expanded>(&json).err().unwrap();
+    assert!(err.to_string().contains("a non-negative count"));
+
+    // The conversion ratio, which carries a DurationHistogram's unit, survives
+    let mut d = DurationHistogram::new(
+        TimeUnit::Microseconds,
+        Duration::from_micros(1),
+        Duration::from_secs(1),
+        3,
+    )
+    .unwrap();
+    d.record(Duration::from_millis(5));
+    let json = serde_json::to_string(&Expanded(d.histogram().clone())).unwrap();
+    assert!(json.contains(r#""conversion_ratio":1e-6"#));
+    let Expanded(expanded) = serde_json::from_str(&json).unwrap();
+    let encoded = expanded.encode_to_vec().unwrap();
+    assert_eq!(DurationHistogram::decode_from_slice(&encoded).unwrap(), d);
 }
 
 #[test]
 fn test_bad_decode() {
     assert!(Histogram::decode(&"hello, world".to_string()).is_err())
 }
+
+#[test]
+fn test_atomic_record() {
+    let h = AtomicHistogram::new(1, 3600 * 1000 * 1000, 3).unwrap();
+
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let h = &h;
+            s.spawn(move || {
+                for _ in 0..1000 {
+                    assert!(h.record_value(1000 * (t + 1)));
+                }
+                assert!(h.record_values(100000000, 10));
+            });
+        }
+    });
+
+    let snap = h.snapshot();
+
+    assert_eq!(snap.total_count(), 4040);
+    assert_eq!(snap.min(), 1000);
+    assert!(snap.values_are_equivalent(snap.max(), 100000000));
+    assert_eq!(snap.count_at_value(2000), 1000);
+    assert!(!h.record_value(3600 * 1000 * 1000 * 10));
+}
+
+#[test]
+fn test_interval_recorder() {
+    let rec = IntervalRecorder::new(1, 3600 * 1000 * 1000, 3).unwrap();
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for v in 1..=1000 {
+                    assert!(rec.record_value(v));
+                }
+            });
+        }
+    });
+
+    let first = rec.sample();
+    assert_eq!(first.total_count(), 4000);
+    assert_eq!(first.max(), 1000);
+
+    rec.record_values(5000, 10);
+    rec.record_corrected_value(100000, 10000);
+
+    let second = rec.sample_and_recycle(first);
+    assert_eq!(second.total_count(), 20);
+    assert_eq!(second.min(), 5000);
+
+    let third = rec.sample_and_recycle(second);
+    assert_eq!(third.total_count(), 0);
+}
+
+#[test]
+fn test_phaser_double_buffer() {
+    use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+
+    let phaser = WriterReaderPhaser::new().unwrap();
+    let active = AtomicUsize::new(0);
+    let buffers = [AtomicI64::new(0), AtomicI64::new(0)];
+    let done = AtomicBool::new(false);
+    let mut total = 0;
+
+    std::thread::scope(|s| {
+        let writers: Vec<_> = (0..4)
+            .map(|_| {
+                s.spawn(|| {
+                    for _ in 0..10000 {
+                        let _guard = phaser.writer_enter();
+                        buffers[active.load(Ordering::SeqCst)].fetch_add(1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+
+        s.spawn(|| {
+            for w in writers {
+                w.join().unwrap();
+            }
+            done.store(true, Ordering::SeqCst);
+        });
+
+        loop {
+            let finished = done.load(Ordering::SeqCst);
+
+            let mut reader = phaser.reader_lock();
+            let inactive = active.fetch_xor(1, Ordering::SeqCst);
+            reader.flip_phase(0);
+            total += buffers[inactive].swap(0, Ordering::SeqCst);
+            drop(reader);
+
+            if finished {
+                break;
+            }
+        }
+    });
+
+    let remaining: i64 = buffers.iter().map(|b| b.load(Ordering::SeqCst)).sum();
+    assert_eq!(total + remaining, 40000);
+}
+
+// V2 compressed encoding of a (1, 3600000000, 3) histogram holding `i % 10 + 1` counts of
+// `i * i * 50 + 100` for i in 0..200, and one of 100000000. The V2 payload was produced by the
+// pure Rust hdrhistogram crate's `V2Serializer` and deflated with zlib at its default level, as
+// HdrHistogram Java's `encodeIntoCompressedByteBuffer` does, so it's independent of this crate's
+// encoder.
+#[cfg(feature = "hdr_log")]
+const V2_COMPRESSED: &[u8] = &[
+    0x1c, 0x84, 0x93, 0x14, 0x00, 0x00, 0x01, 0xb3, 0x78, 0x9c, 0x2d, 0xcc, 0xb1, 0x6b, 0x14, 0x41,
+    0x14, 0x06, 0xf0, 0x7b, 0x6f, 0x5f, 0xc6, 0xe1, 0x31, 0x0c, 0xc3, 0x38, 0xcd, 0xb1, 0x0e, 0xc3,
+    0xb2, 0x1c, 0xcb, 0x72, 0x2c, 0xcb, 0x71, 0x2c, 0x72, 0x1c, 0xe1, 0x08, 0x47, 0x38, 0x8e, 0x10,
+    0x52, 0x04, 0x91, 0x10, 0x24, 0x48, 0x90, 0x20, 0x62, 0x61, 0x61, 0x21, 0x16, 0x21, 0x44, 0x91,
+    0x60, 0x21, 0x72, 0x48, 0x90, 0x94, 0xb1, 0x49, 0x95, 0x22, 0x58, 0x88, 0x58, 0x88, 0x45, 0xb0,
+    0x90, 0x54, 0x0a, 0xe2, 0x1f, 0x60, 0x61, 0xa1, 0x29, 0x2c, 0x05, 0x47, 0xe2, 0x2b, 0xbe, 0x0f,
+    0x7e, 0x7c, 0xbc, 0x2b, 0x8f, 0x66, 0x97, 0x5b, 0x2d, 0x78, 0xdf, 0xba, 0xb8, 0xe4, 0x7f, 0xc3,
+    0xbf, 0xf8, 0x32, 0x7b, 0xd5, 0x1a, 0xfd, 0xba, 0x80, 0x53, 0xc0, 0x9b, 0x74, 0x84, 0xe2, 0x3c,
+    0x91, 0x6f, 0xe6, 0x78, 0xe7, 0x92, 0xfa, 0x28, 0xf5, 0x81, 0x30, 0x3f, 0xe6, 0xec, 0x89, 0x70,
+    0x5f, 0x13, 0xdc, 0x23, 0x7a, 0x47, 0x62, 0x46, 0xf2, 0x0c, 0xf9, 0x3b, 0xaa, 0x9d, 0x44, 0xbf,
+    0x4c, 0xcc, 0x49, 0x62, 0x7f, 0x82, 0x3b, 0x07, 0xfc, 0x03, 0xb4, 0x87, 0x62, 0x1f, 0xe5, 0x21,
+    0xf2, 0x31, 0xaa, 0xdf, 0xa0, 0x0f, 0xc1, 0x1c, 0x81, 0x3d, 0x06, 0xf7, 0x1a, 0xf0, 0x2d, 0xd0,
+    0x07, 0x10, 0xa7, 0x20, 0xcf, 0x80, 0x3f, 0x83, 0xfa, 0x06, 0x7a, 0x17, 0xcc, 0x3d, 0x7b, 0xdf,
+    0x3d, 0xc0, 0x87, 0xb4, 0x2d, 0x76, 0x41, 0x3e, 0x06, 0x7e, 0x02, 0xea, 0x29, 0xe8, 0x67, 0x60,
+    0x9e, 0x83, 0x7d, 0x01, 0x6e, 0x1f, 0xf0, 0x00, 0xe8, 0x86, 0x58, 0x91, 0x2b, 0xbc, 0xaa, 0x56,
+    0xf5, 0x35, 0x73, 0xdd, 0xae, 0xb9, 0x75, 0x5c, 0x8f, 0xb6, 0x21, 0x37, 0x78, 0x53, 0x6d, 0xea,
+    0x5b, 0x66, 0xcb, 0x6e, 0xb9, 0xdb, 0x78, 0x87, 0xee, 0x8a, 0x65, 0xd9, 0xf0, 0x40, 0x5d, 0xd5,
+    0x03, 0x33, 0xb0, 0x43, 0x37, 0xc4, 0x21, 0xcd, 0x8b, 0x79, 0x39, 0xe2, 0x91, 0x1a, 0xe9, 0x05,
+    0xb3, 0x60, 0xc7, 0x6e, 0x8c, 0x63, 0x5a, 0x14, 0x8b, 0x72, 0xc2, 0x13, 0x35, 0xd1, 0x53, 0x33,
+    0xb5, 0x53, 0xb7, 0x84, 0x4b, 0xb4, 0x2c, 0x1a, 0xd9, 0xe1, 0x8e, 0x2a, 0x74, 0x61, 0x0a, 0x5b,
+    0xb8, 0x02, 0x4b, 0x2a, 0x45, 0x29, 0x4b, 0x2e, 0x55, 0x57, 0x77, 0x4d, 0x69, 0x2b, 0xd7, 0xc5,
+    0x2e, 0x55, 0xa2, 0x92, 0x15, 0xd7, 0xaa, 0xd2, 0xb5, 0xa9, 0x6d, 0xed, 0x6a, 0xec, 0x51, 0x2d,
+    0x7a, 0xb2, 0xc7, 0x3d, 0xd5, 0xd7, 0x7d, 0xd3, 0xb3, 0x8d, 0xeb, 0x63, 0x9f, 0x9a, 0xf8, 0xaf,
+    0x89, 0xbb, 0xb6, 0x4e, 0x4d, 0xdb, 0xa6, 0xae, 0x8d, 0x29, 0xa5, 0x22, 0x95, 0x29, 0xa7, 0x2a,
+    0x8d, 0xe6, 0xa3, 0xf9, 0x68, 0x5e, 0x78, 0xe9, 0xd9, 0x2b, 0xaf, 0xbd, 0x09, 0xd6, 0xbb, 0x80,
+    0x9e, 0x82, 0x08, 0x32, 0x70, 0x50, 0x41, 0x07, 0x93, 0xd9, 0xe0, 0x32, 0x0c, 0x94, 0x89, 0x4c,
+    0x66, 0x9c, 0xa9, 0x4c, 0x67, 0xd1, 0xf2, 0x68, 0x39, 0xe5, 0xd1, 0x72, 0xce, 0x55, 0xae, 0x3b,
+    0x26, 0x8f, 0xf6, 0x69, 0x0d, 0xff, 0x02, 0xb3, 0x92, 0x4f, 0xb4,
+];
+
+#[cfg(feature = "hdr_log")]
+fn v2_fixture_histogram() -> Histogram {
+    let mut h = Histogram::new(1, 3600000000, 3).unwrap();
+    for i in 0..200 {
+        h.record_values(i * i * 50 + 100, i % 10 + 1);
+    }
+    h.record_value(100000000);
+    h
+}
+
+#[test]
+#[cfg(feature = "hdr_log")]
+fn test_binary_codec() {
+    let Loaded { raw, cor, .. } = load_histograms();
+
+    let enc = raw.encode_to_vec().unwrap();
+    let dec = Histogram::decode_from_slice(&enc).unwrap();
+
+    assert_eq!(raw.total_count(), dec.total_count());
+    assert_eq!(raw.count_at_value(1000), dec.count_at_value(1000));
+    assert_eq!(raw.max(), dec.max());
+
+    let mut stream = Vec::new();
+    raw.encode_into(&mut stream).unwrap();
+    cor.encode_into(&mut stream).unwrap();
+    assert_eq!(&stream[..enc.len()], &enc[..]);
+
+    let mut reader = &stream[..];
+    let dec_raw = Histogram::decode_from(&mut reader).unwrap();
+    let dec_cor = Histogram::decode_from(&mut reader).unwrap();
+    assert!(reader.is_empty());
+
+    assert_eq!(raw.total_count(), dec_raw.total_count());
+    assert_eq!(cor.total_count(), dec_cor.total_count());
+    assert_eq!(
+        cor.value_at_percentile(90.0),
+        dec_cor.value_at_percentile(90.0)
+    );
+}
+
+#[test]
+#[cfg(feature = "hdr_log")]
+fn test_binary_decode_fixture() {
+    let h = Histogram::decode_from_slice(V2_COMPRESSED).unwrap();
+
+    assert_eq!(h.total_count(), 1101);
+    assert_eq!(h.count_at_value(100), 1);
+    assert_eq!(h.count_at_value(199 * 199 * 50 + 100), 10);
+    assert_eq!(h.min(), 100);
+    assert!(h.values_are_equivalent(h.max(), 100000000));
+    assert_eq!(h, v2_fixture_histogram());
+
+    let h = Histogram::decode_from(V2_COMPRESSED).unwrap();
+    assert_eq!(h.total_count(), 1101);
+}
+
+#[test]
+#[cfg(feature = "rust_deflate")]
+fn test_binary_encode_fixture() {
+    use miniz_oxide::inflate::decompress_to_vec_zlib;
+
+    // miniz_oxide doesn't compress exactly like zlib, so compare the V2 payloads themselves.
+    let enc = v2_fixture_histogram().encode_to_vec().unwrap();
+    assert_eq!(
+        decompress_to_vec_zlib(&enc[8..]).unwrap(),
+        decompress_to_vec_zlib(&V2_COMPRESSED[8..]).unwrap()
+    );
+}
+
+#[test]
+#[cfg(feature = "rust_deflate")]
+fn test_binary_encode_layout() {
+    use miniz_oxide::{deflate::compress_to_vec_zlib, inflate::decompress_to_vec_zlib};
+
+    // The V2 payload hdr_encode_compressed writes for counts of 1 at 1 and 3: a single zero is
+    // still written as a run of -1, and the normalizing index offset is written as stored.
+    let mut h = Histogram::new(1, 2048, 3).unwrap();
+    h.record_value(1);
+    h.record_value(3);
+
+    let mut v2 = Vec::new();
+    v2.extend_from_slice(&0x1c849313i32.to_be_bytes());
+    v2.extend_from_slice(&4i32.to_be_bytes());
+    v2.extend_from_slice(&0i32.to_be_bytes());
+    v2.extend_from_slice(&3i32.to_be_bytes());
+    v2.extend_from_slice(&1i64.to_be_bytes());
+    v2.extend_from_slice(&2048i64.to_be_bytes());
+    v2.extend_from_slice(&1.0f64.to_bits().to_be_bytes());
+    v2.extend_from_slice(&[0x01, 0x02, 0x01, 0x02]);
+
+    let enc = h.encode_to_vec().unwrap();
+    assert_eq!(decompress_to_vec_zlib(&enc[8..]).unwrap(), v2);
+
+    v2[8..12].copy_from_slice(&2i32.to_be_bytes());
+    let compressed = compress_to_vec_zlib(&v2, 6);
+    let mut shifted = enc[..4].to_vec();
+    shifted.extend_from_slice(&(compressed.len() as i32).to_be_bytes());
+    shifted.extend_from_slice(&compressed);
+
+    let h = Histogram::decode_from_slice(&shifted).unwrap();
+    let enc = h.encode_to_vec().unwrap();
+    let reencoded = decompress_to_vec_zlib(&enc[8..]).unwrap();
+    assert_eq!(reencoded[8..12], 2i32.to_be_bytes());
+    assert_eq!(reencoded[40..44], [0x01, 0x02, 0x01, 0x02]);
+    assert_eq!(Histogram::decode_from_slice(&enc).unwrap(), h);
+}
+
+#[test]
+#[cfg(feature = "hdr_log")]
+fn test_codec_errors() {
+    let mut bad_cookie = V2_COMPRESSED.to_vec();
+    bad_cookie[3] = 0;
+    assert!(matches!(
+        Histogram::decode_from_slice(&bad_cookie),
+        Err(HistogramErr::BadCookie(0x1c849300))
+    ));
+
+    // Base64 of a 0x1c849222 cookie followed by zeros
+    assert!(matches!(
+        Histogram::decode(&"HISSIgAAAAAAAAAA".to_string()),
+        Err(HistogramErr::BadCookie(0x1c849222))
+    ));
+
+    // Too short to hold a cookie
+    assert!(matches!(
+        Histogram::decode(&"HIS".to_string()),
+        Err(HistogramErr::Truncated)
+    ));
+    assert!(matches!(
+        Histogram::decode_from_slice(&V2_COMPRESSED[..3]),
+        Err(HistogramErr::Truncated)
+    ));
+
+    let mut bad_deflate = V2_COMPRESSED.to_vec();
+    bad_deflate[8] = 0;
+    assert!(matches!(
+        Histogram::decode_from_slice(&bad_deflate),
+        Err(HistogramErr::InflateFailed)
+    ));
+}
+
+#[test]
+#[cfg(feature = "hdr_log")]
+fn test_bad_binary_decode() {
+    assert!(Histogram::decode_from_slice(b"hello, world").is_err());
+    assert!(Histogram::decode_from_slice(&V2_COMPRESSED[..40]).is_err());
+    assert!(matches!(
+        Histogram::decode_from(&V2_COMPRESSED[..40]),
+        Err(HistogramErr::Io(_))
+    ));
+
+    // A header claiming a ~2GiB payload which isn't there
+    assert!(matches!(
+        Histogram::decode_from(&[0x1c, 0x84, 0x93, 0x14, 0x7f, 0xff, 0xff, 0xff, 0x78, 0x9c][..]),
+        Err(HistogramErr::Io(_))
+    ));
+}
+
+#[test]
+#[cfg(feature = "rust_deflate")]
+fn test_binary_decode_bomb() {
+    // A (1, 1000, 3) header followed by 16MiB of zeros, which deflates to a few KiB
+    let bomb = |payload_len: i32| {
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(&0x1c849313u32.to_be_bytes());
+        encoded.extend_from_slice(&payload_len.to_be_bytes());
+        encoded.extend_from_slice(&0i32.to_be_bytes());
+        encoded.extend_from_slice(&3i32.to_be_bytes());
+        encoded.extend_from_slice(&1i64.to_be_bytes());
+        encoded.extend_from_slice(&1000i64.to_be_bytes());
+        encoded.extend_from_slice(&1f64.to_bits().to_be_bytes());
+        encoded.resize(encoded.len() + (16 << 20), 0);
+
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&encoded, 6);
+        let mut buffer = 0x1c849314u32.to_be_bytes().to_vec();
+        buffer.extend_from_slice(&(compressed.len() as i32).to_be_bytes());
+        buffer.extend_from_slice(&compressed);
+        buffer
+    };
+
+    // More payload than the counts could ever need
+    assert!(Histogram::decode_from_slice(&bomb(i32::MAX)).is_err());
+    // A plausible length, followed by far more data than it claims
+    assert!(Histogram::decode_from_slice(&bomb(100)).is_err());
+}
+
+#[test]
+#[cfg(feature = "hdr_log")]
+fn test_log_writer() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let Loaded { raw, cor, .. } = load_histograms();
+    let start = UNIX_EPOCH + Duration::from_millis(1441812123120);
+
+    let mut log = LogWriter::new(Vec::new());
+    log.write_comment("Logged with hdrhistogram-c").unwrap();
+    log.write_header(start).unwrap();
+    log.write_histogram(start, Duration::from_millis(1000), &raw, None)
+        .unwrap();
+    log.write_histogram(
+        start + Duration::from_millis(1000),
+        Duration::from_millis(1500),
+        &cor,
+        Some("corrected"),
+    )
+    .unwrap();
+    assert!(log
+        .write_histogram(start, Duration::from_secs(1), &raw, Some("bad tag"))
+        .is_err());
+
+    let out = String::from_utf8(log.into_inner()).unwrap();
+    let lines: Vec<_> = out.lines().collect();
+
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[0], "#[Logged with hdrhistogram-c]");
+    assert_eq!(lines[1], "#[Histogram log format version 1.3]");
+    assert_eq!(
+        lines[2],
+        "#[StartTime: 1441812123.120 (seconds since epoch), 2015-09-09T15:22:03.120Z]"
+    );
+    assert_eq!(
+        lines[3],
+        "#[BaseTime: 1441812123.120 (seconds since epoch)]"
+    );
+    assert_eq!(
+        lines[4],
+        "\"StartTimestamp\",\"Interval_Length\",\"Interval_Max\",\"Interval_Compressed_Histogram\""
+    );
+
+    let fields: Vec<_> = lines[5].split(',').collect();
+    assert_eq!(&fields[..2], &["0.000", "1.000"]);
+    assert_eq!(fields[2], format!("{:.3}", raw.max() as f64 / 1000000.0));
+    let dec = Histogram::decode(&fields[3].to_string()).unwrap();
+    assert_eq!(dec.total_count(), raw.total_count());
+
+    let fields: Vec<_> = lines[6].split(',').collect();
+    assert_eq!(&fields[..3], &["Tag=corrected", "1.000", "1.500"]);
+    let dec = Histogram::decode(&fields[4].to_string()).unwrap();
+    assert_eq!(dec.total_count(), cor.total_count());
+}
+
+#[test]
+#[cfg(feature = "hdr_log")]
+fn test_log_reader() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let Loaded { raw, cor, .. } = load_histograms();
+    let start = UNIX_EPOCH + Duration::from_secs(1441812123);
+
+    let mut log = LogWriter::new(Vec::new());
+    log.write_header(start).unwrap();
+    for i in 0..10 {
+        let t = start + Duration::from_secs(i);
+        log.write_histogram(t, Duration::from_secs(1), &raw, None)
+            .unwrap();
+        log.write_histogram(t, Duration::from_secs(1), &cor, Some("cor"))
+            .unwrap();
+    }
+    let log = log.into_inner();
+
+    let mut reader = LogReader::new(&log[..]);
+    let entries: Vec<_> = reader.by_ref().map(Result::unwrap).collect();
+    assert_eq!(entries.len(), 20);
+    assert_eq!(reader.start_time(), Some(1441812123.0));
+    assert_eq!(reader.base_time(), Some(1441812123.0));
+
+    assert_eq!(entries[0].start_timestamp, 1441812123.0);
+    assert_eq!(entries[0].interval_length, 1.0);
+    assert_eq!(entries[0].tag, None);
+    assert_eq!(entries[0].histogram.total_count(), raw.total_count());
+    assert_eq!(entries[1].tag.as_deref(), Some("cor"));
+    assert_eq!(entries[1].histogram.total_count(), cor.total_count());
+    assert_eq!(entries[19].start_timestamp, 1441812132.0);
+
+    let tagged: Vec<_> = LogReader::new(&log[..])
+        .with_tag(Some("cor"))
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(tagged.len(), 10);
+    assert!(tagged.iter().all(|e| e.tag.as_deref() == Some("cor")));
+
+    let ranged: Vec<_> = LogReader::new(&log[..])
+        .with_tag(None)
+        .with_time_range(1441812125.0, 1441812128.0)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(ranged.len(), 3);
+    assert_eq!(ranged[0].start_timestamp, 1441812125.0);
+    assert!(ranged.iter().all(|e| e.tag.is_none()));
+}
+
+#[test]
+#[cfg(feature = "hdr_log")]
+fn test_log_reader_formats() {
+    let Loaded { raw, .. } = load_histograms();
+    let enc = raw.encode().unwrap();
+
+    // No BaseTime, absolute timestamps, and a legacy entry without the max column.
+    let log = format!(
+        "#[A comment]\n\
+         #[StartTime: 1441812123.120 (seconds since epoch), Wed Sep 09 08:22:03 PDT 2015]\n\
+         \"StartTimestamp\",\"Interval_Length\",\"Interval_Max\",\"Interval_Compressed_Histogram\"\n\
+         1441812123.120,1.000,100.000,{enc}\n\
+         \n\
+         1441812124.120,1.000,{enc}\n\
+         Tag=x,1441812125.120,oops,100.000,{enc}\n",
+        enc = enc
+    );
+
+    let mut reader = LogReader::new(log.as_bytes());
+
+    let first = reader.next().unwrap().unwrap();
+    assert_eq!(first.start_timestamp, 1441812123.120);
+    assert_eq!(first.interval_max, Some(100.0));
+    assert_eq!(reader.base_time(), Some(0.0));
+
+    let second = reader.next().unwrap().unwrap();
+    assert_eq!(second.interval_max, None);
+    assert_eq!(second.histogram.total_count(), raw.total_count());
+
+    match reader.next() {
+        Some(Err(HistogramErr::LogFormat { line, .. })) => assert_eq!(line, 7),
+        _ => panic!("expected a format error"),
+    }
+    assert!(reader.next().is_none());
+
+    // Relative timestamps without a BaseTime are resolved against the StartTime.
+    let log = format!(
+        "#[StartTime: 1441812123.000 (seconds since epoch)]\n0.500,1.000,1.000,{}\n",
+        enc
+    );
+    let entry = LogReader::new(log.as_bytes()).next().unwrap().unwrap();
+    assert_eq!(entry.start_timestamp, 1441812123.5);
+
+    let bad = "0.000,1.000,1.000,HISTnotbase64\n";
+    assert!(matches!(
+        LogReader::new(bad.as_bytes()).next(),
+        Some(Err(HistogramErr::LogFormat { line: 1, .. }))
+    ));
+}